@@ -4,6 +4,25 @@ use super::c_str;
 
 use libc::*;
 
+mod dir;
+mod error;
+mod file;
+mod ops;
+mod path;
+pub mod result;
+mod walk;
+#[cfg(feature = "mock_fs")]
+pub mod mock;
+#[cfg(feature = "mock_fs")]
+pub use mock as fs_impl;
+pub use dir::{read_dir, DirEntry, ReadDir};
+pub use error::{Error, ErrorKind};
+pub use file::File;
+pub use ops::{copy, copy_dir_all, create_dir_all, exists, metadata, move_dir, read, read_to_string, write, Metadata};
+pub use path::{Component, Components, Path, PathBuf};
+pub use walk::{glob_match, walk_dir, WalkDir, WalkEntry};
+pub use super::io::SeekFrom;
+
 bitflags! {
     #[repr(C)]
     pub struct WriteOptions : u32 {
@@ -30,7 +49,7 @@ bitflags! {
 }
 
 #[repr(i8)]
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DirectoryEntryType {
     Directory,
     File
@@ -61,6 +80,24 @@ impl Clone for DirectoryEntry {
     }
 }
 
+impl DirectoryEntry {
+    pub fn name_bytes(&self) -> &[u8] {
+        let len = self.name.iter().position(|&c| c == 0).unwrap_or(self.name.len());
+        unsafe {
+            core::slice::from_raw_parts(self.name.as_ptr() as *const u8, len)
+        }
+    }
+
+    // Names that aren't valid UTF-8 are cut off at the first invalid byte
+    pub fn name(&self) -> &str {
+        let bytes = self.name_bytes();
+        match core::str::from_utf8(bytes) {
+            Ok(name) => name,
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) }
+        }
+    }
+}
+
 pub const ENTRY_NAME_BYTE_LENGTH_MAX: usize = 768;
 pub const MOUNT_NAME_LENGTH_MAX: usize      = 15;
 
@@ -75,6 +112,7 @@ pub struct FileHandle(pub u64);
 #[repr(transparent)]
 pub struct DirectoryHandle(pub u64);
 
+#[cfg(not(feature = "mock_fs"))]
 pub mod fs_impl {
     use super::super::Result;
     use libc::*;
@@ -259,39 +297,39 @@ pub fn set_alloc_funcs(allocator: AllocatorFunction, deallocator: DeallocatorFun
 }
 
 #[dev_inline]
-pub fn mount_save_data<S: AsRef<str>>(name: S) -> Result<(), NxResult> {
+pub fn mount_save_data<S: AsRef<str>>(name: S) -> Result<(), Error> {
     unsafe {
         let name = name.as_ref();
         let result = fs_impl::MountSaveDataForDebug(c_str!(name));
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn mount_sd_card<S: AsRef<str>>(name: S) -> Result<(), NxResult> {
+pub fn mount_sd_card<S: AsRef<str>>(name: S) -> Result<(), Error> {
     unsafe {
         let name = name.as_ref();
         let result = fs_impl::MountSdCardForDebug(c_str!(name));
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn mount_rom<B: AsMut<[u8]>, S: AsRef<str>>(name: S, mut buffer: B) -> Result<(), NxResult> {
+pub fn mount_rom<B: AsMut<[u8]>, S: AsRef<str>>(name: S, mut buffer: B) -> Result<(), Error> {
     unsafe {
         let name = name.as_ref();
         let buffer = buffer.as_mut();
         let result = fs_impl::MountRom(c_str!(name), buffer.as_mut_ptr() as _, buffer.len());
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn query_rom_cache_size() -> Result<usize, NxResult> {
+pub fn query_rom_cache_size() -> Result<usize, Error> {
     unsafe {
         let mut size = 0;
         let result = fs_impl::QueryMountRomCacheSize(&mut size);
-        get_rust_result!(result, size)
+        get_rust_result!(result, size).map_err(Error::from)
     }
 }
 
@@ -304,40 +342,40 @@ pub fn unmount<S: AsRef<str>>(name: S) {
 }
 
 #[dev_inline]
-pub fn create_file<S: AsRef<str>>(name: S, initial_size: isize) -> Result<(), NxResult> {
+pub fn create_file<S: AsRef<str>>(name: S, initial_size: isize) -> Result<(), Error> {
     unsafe {
         let name = name.as_ref();
         let result = fs_impl::CreateFile(c_str!(name), initial_size);
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn delete_file<S: AsRef<str>>(name: S) -> Result<(), NxResult> {
+pub fn delete_file<S: AsRef<str>>(name: S) -> Result<(), Error> {
     unsafe {
         let name = name.as_ref();
         let result = fs_impl::DeleteFile(c_str!(name));
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn rename_file<S: AsRef<str>, R: AsRef<str>>(old: S, new: R) -> Result<(), NxResult> {
+pub fn rename_file<S: AsRef<str>, R: AsRef<str>>(old: S, new: R) -> Result<(), Error> {
     unsafe {
         let old = old.as_ref();
         let new = new.as_ref();
         let result = fs_impl::RenameFile(c_str!(old), c_str!(new));
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn open_file<S: AsRef<str>>(path: S, mode: OpenMode) -> Result<FileHandle, NxResult> {
+pub fn open_file<S: AsRef<str>>(path: S, mode: OpenMode) -> Result<FileHandle, Error> {
     unsafe {
         let path = path.as_ref();
         let mut handle = FileHandle(0);
         let result = fs_impl::OpenFile(&mut handle, c_str!(path), mode);
-        get_rust_result!(result, handle)
+        get_rust_result!(result, handle).map_err(Error::from)
     }
 }
 
@@ -349,44 +387,44 @@ pub fn close_file(handle: FileHandle) {
 }
 
 #[dev_inline]
-pub fn read_file(handle: FileHandle, offset: isize, buffer: *mut c_void, buffer_size: usize) -> Result<usize, NxResult> {
+pub fn read_file(handle: FileHandle, offset: isize, buffer: *mut c_void, buffer_size: usize) -> Result<usize, Error> {
     unsafe {
         let mut read_bytes = 0;
         let result = fs_impl::ReadFile(&mut read_bytes, handle, offset, buffer, buffer_size);
-        get_rust_result!(result, read_bytes)
+        get_rust_result!(result, read_bytes).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn write_file(handle: FileHandle, offset: isize, buffer: *const c_void, buffer_size: usize, options: WriteOptions) -> Result<(), NxResult> {
+pub fn write_file(handle: FileHandle, offset: isize, buffer: *const c_void, buffer_size: usize, options: WriteOptions) -> Result<(), Error> {
     unsafe {
         let result = fs_impl::WriteFile(handle, offset, buffer, buffer_size, &options);
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn flush_file(handle: FileHandle) -> Result<(), NxResult> {
+pub fn flush_file(handle: FileHandle) -> Result<(), Error> {
     unsafe {
         let result = fs_impl::FlushFile(handle);
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn resize_file(handle: FileHandle, new_size: isize) -> Result<(), NxResult> {
+pub fn resize_file(handle: FileHandle, new_size: isize) -> Result<(), Error> {
     unsafe {
         let result = fs_impl::SetFileSize(handle, new_size);
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn get_file_size(handle: FileHandle) -> Result<isize, NxResult> {
+pub fn get_file_size(handle: FileHandle) -> Result<isize, Error> {
     unsafe {
         let mut size = 0;
         let result = fs_impl::GetFileSize(&mut size, handle);
-        get_rust_result!(result, size)
+        get_rust_result!(result, size).map_err(Error::from)
     }
 }
 
@@ -398,16 +436,16 @@ pub fn file_open_mode(handle: FileHandle) -> OpenMode {
 }
 
 #[dev_inline]
-pub fn create_directory<S: AsRef<str>>(path: S) -> Result<(), NxResult> {
+pub fn create_directory<S: AsRef<str>>(path: S) -> Result<(), Error> {
     unsafe {
         let path = path.as_ref();
         let result = fs_impl::CreateDirectory(c_str!(path));
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn delete_directory<S: AsRef<str>>(path: S, recursive: bool) -> Result<(), NxResult> {
+pub fn delete_directory<S: AsRef<str>>(path: S, recursive: bool) -> Result<(), Error> {
     unsafe {
         let path = path.as_ref();
         let result = if recursive {
@@ -415,48 +453,48 @@ pub fn delete_directory<S: AsRef<str>>(path: S, recursive: bool) -> Result<(), N
         } else {
             fs_impl::DeleteDirectory(c_str!(path))
         };
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn rename_directory<S: AsRef<str>, R: AsRef<str>>(old: S, new: S) -> Result<(), NxResult> {
+pub fn rename_directory<S: AsRef<str>, R: AsRef<str>>(old: S, new: R) -> Result<(), Error> {
     unsafe {
         let old = old.as_ref();
         let new = new.as_ref();
 
         let result = fs_impl::RenameDirectory(c_str!(old), c_str!(new));
-        get_rust_result!(result, ())
+        get_rust_result!(result, ()).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn get_entry_type<S: AsRef<str>>(path: S) -> Result<DirectoryEntryType, NxResult> {
+pub fn get_entry_type<S: AsRef<str>>(path: S) -> Result<DirectoryEntryType, Error> {
     unsafe {
         let path = path.as_ref();
 
         let mut entry_type = DirectoryEntryType::File;
         let result = fs_impl::GetEntryType(&mut entry_type, c_str!(path));
-        get_rust_result!(result, entry_type)
+        get_rust_result!(result, entry_type).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn read_directory_entries<M: AsMut<[DirectoryEntry]>>(mut entries: M, handle: DirectoryHandle) -> Result<isize, NxResult> {
+pub fn read_directory_entries<M: AsMut<[DirectoryEntry]>>(mut entries: M, handle: DirectoryHandle) -> Result<isize, Error> {
     unsafe {
         let entries = entries.as_mut();
         let mut entries_read = 0;
         let result = fs_impl::ReadDirectory(&mut entries_read, entries.as_mut_ptr(), handle, entries.len() as isize);
-        get_rust_result!(result, entries_read)
+        get_rust_result!(result, entries_read).map_err(Error::from)
     }
 }
 
 #[dev_inline]
-pub fn get_directory_entry_count(handle: DirectoryHandle) -> Result<isize, NxResult> {
+pub fn get_directory_entry_count(handle: DirectoryHandle) -> Result<isize, Error> {
     unsafe {
         let mut count = 0;
         let result = fs_impl::GetDirectoryEntryCount(&mut count, handle);
-        get_rust_result!(result, count)
+        get_rust_result!(result, count).map_err(Error::from)
     }
 }
 
@@ -468,12 +506,12 @@ pub fn close_directory(handle: DirectoryHandle) {
 }
 
 #[dev_inline]
-pub fn open_directory<S: AsRef<str>>(path: S, mode: OpenDirectoryMode) -> Result<DirectoryHandle, NxResult> {
+pub fn open_directory<S: AsRef<str>>(path: S, mode: OpenDirectoryMode) -> Result<DirectoryHandle, Error> {
     unsafe {
         let path = path.as_ref();
         
         let mut handle = DirectoryHandle(0);
         let result = fs_impl::OpenDirectory(&mut handle, c_str!(path), mode);
-        get_rust_result!(result, handle)
+        get_rust_result!(result, handle).map_err(Error::from)
     }
 }
\ No newline at end of file
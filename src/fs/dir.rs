@@ -0,0 +1,132 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use super::{Error, DirectoryEntry, DirectoryEntryType, DirectoryHandle, OpenDirectoryMode, Path, PathBuf};
+
+// Number of entries fetched per nn::fs::ReadDirectory call
+const READ_DIR_BATCH_COUNT: usize = 16;
+
+// Owned directory handle which hands out its entries in batches and closes the handle when dropped
+pub struct ReadDir {
+    handle: DirectoryHandle,
+    path: Arc<str>,
+    entries: Vec<DirectoryEntry>,
+    index: usize,
+    count: usize,
+    finished: bool
+}
+
+#[derive(Clone)]
+pub struct DirEntry {
+    parent: Arc<str>,
+    entry: DirectoryEntry
+}
+
+#[dev_inline]
+pub fn read_dir<S: AsRef<str>>(path: S) -> Result<ReadDir, Error> {
+    ReadDir::open(path, OpenDirectoryMode::ALL)
+}
+
+impl ReadDir {
+    #[dev_inline]
+    pub fn open<S: AsRef<str>>(path: S, mode: OpenDirectoryMode) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let handle = super::open_directory(path, mode)?;
+        let mut entries = Vec::with_capacity(READ_DIR_BATCH_COUNT);
+        // DirectoryEntry is plain old data, all zeroes is a valid (empty) entry
+        entries.resize_with(READ_DIR_BATCH_COUNT, || unsafe { core::mem::zeroed() });
+        Ok(Self {
+            handle,
+            path: Arc::from(path),
+            entries,
+            index: 0,
+            count: 0,
+            finished: false
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        Path::new(&*self.path)
+    }
+
+    pub const fn handle(&self) -> DirectoryHandle {
+        self.handle
+    }
+
+    fn fill_batch(&mut self) -> Result<(), Error> {
+        let count = super::read_directory_entries(&mut self.entries[..], self.handle)?;
+        self.index = 0;
+        self.count = count as usize;
+        if self.count == 0 {
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        if self.index >= self.count {
+            if let Err(e) = self.fill_batch() {
+                self.finished = true;
+                return Some(Err(e));
+            }
+            if self.finished {
+                return None;
+            }
+        }
+
+        let entry = self.entries[self.index].clone();
+        self.index += 1;
+        Some(Ok(DirEntry {
+            parent: self.path.clone(),
+            entry
+        }))
+    }
+}
+
+impl Drop for ReadDir {
+    fn drop(&mut self) {
+        super::close_directory(self.handle)
+    }
+}
+
+impl DirEntry {
+    pub fn name(&self) -> &str {
+        self.entry.name()
+    }
+
+    pub fn entry_type(&self) -> DirectoryEntryType {
+        self.entry.entry_type
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry.entry_type == DirectoryEntryType::Directory
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.entry.entry_type == DirectoryEntryType::File
+    }
+
+    // Always 0 for directories
+    pub fn size(&self) -> u64 {
+        self.entry.size as u64
+    }
+
+    pub fn parent(&self) -> &Path {
+        Path::new(&*self.parent)
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.parent().join(self.name())
+    }
+
+    pub fn raw(&self) -> &DirectoryEntry {
+        &self.entry
+    }
+}
@@ -0,0 +1,243 @@
+use core::fmt;
+use core::ops::Range;
+use super::super::Result as NxResult;
+use super::result;
+
+// Description ranges nn::fs groups its results into
+const USABLE_SPACE_NOT_ENOUGH: Range<u32> = 30..46;
+const NOT_FOUND: Range<u32> = 1001..1003;
+const SD_CARD_ACCESS_FAILED: Range<u32> = 2000..2500;
+const GAME_CARD_ACCESS_FAILED: Range<u32> = 2500..3000;
+const ALLOCATION_MEMORY_FAILED: Range<u32> = 3200..3500;
+const MMC_ACCESS_FAILED: Range<u32> = 3500..4000;
+const DATA_CORRUPTED: Range<u32> = 4000..5000;
+const INVALID_PATH: Range<u32> = 6002..6030;
+const INVALID_PATH_FOR_OPERATION: Range<u32> = 6030..6060;
+const PRECONDITION_VIOLATION: Range<u32> = 6000..6300;
+const INVALID_OPERATION_FOR_OPEN_MODE: Range<u32> = 6200..6300;
+const UNSUPPORTED_OPERATION: Range<u32> = 6300..6400;
+const PERMISSION_DENIED: Range<u32> = 6400..6450;
+const ENTITY_NOT_FOUND: Range<u32> = 6600..6700;
+const OUT_OF_RESOURCE: Range<u32> = 6700..6800;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    OutOfSpace,
+    TargetLocked,
+    DirectoryNotEmpty,
+    InvalidPath,
+    NotMounted,
+    // SD card, game card or internal storage could not be accessed
+    StorageAccessFailed,
+    DataCorrupted,
+    OutOfMemory,
+    OutOfResource,
+    Unsupported,
+    InvalidInput,
+    InvalidData,
+    UnexpectedEof,
+    WriteZero,
+    Other
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Error {
+    kind: ErrorKind,
+    result: Option<NxResult>
+}
+
+impl ErrorKind {
+    pub fn from_result(result: NxResult) -> Self {
+        if result.module() != result::MODULE {
+            return ErrorKind::Other;
+        }
+
+        match result {
+            result::PATH_NOT_FOUND => return ErrorKind::NotFound,
+            result::PATH_ALREADY_EXISTS | result::MOUNT_NAME_ALREADY_EXISTS => return ErrorKind::AlreadyExists,
+            result::TARGET_LOCKED => return ErrorKind::TargetLocked,
+            result::DIRECTORY_NOT_EMPTY => return ErrorKind::DirectoryNotEmpty,
+            result::NOT_MOUNTED => return ErrorKind::NotMounted,
+            result::INVALID_MOUNT_NAME => return ErrorKind::InvalidPath,
+            result::NOT_IMPLEMENTED => return ErrorKind::Unsupported,
+            _ => {}
+        }
+
+        let description = result.description();
+        let in_range = |range: Range<u32>| range.contains(&description);
+        if in_range(USABLE_SPACE_NOT_ENOUGH) {
+            ErrorKind::OutOfSpace
+        } else if in_range(NOT_FOUND) || in_range(ENTITY_NOT_FOUND) {
+            ErrorKind::NotFound
+        } else if in_range(SD_CARD_ACCESS_FAILED) || in_range(GAME_CARD_ACCESS_FAILED) || in_range(MMC_ACCESS_FAILED) {
+            ErrorKind::StorageAccessFailed
+        } else if in_range(ALLOCATION_MEMORY_FAILED) {
+            ErrorKind::OutOfMemory
+        } else if in_range(DATA_CORRUPTED) {
+            ErrorKind::DataCorrupted
+        } else if in_range(INVALID_PATH) || in_range(INVALID_PATH_FOR_OPERATION) {
+            ErrorKind::InvalidPath
+        } else if in_range(INVALID_OPERATION_FOR_OPEN_MODE) || in_range(PERMISSION_DENIED) {
+            ErrorKind::PermissionDenied
+        } else if in_range(PRECONDITION_VIOLATION) {
+            ErrorKind::InvalidInput
+        } else if in_range(UNSUPPORTED_OPERATION) {
+            ErrorKind::Unsupported
+        } else if in_range(OUT_OF_RESOURCE) {
+            ErrorKind::OutOfResource
+        } else {
+            ErrorKind::Other
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "entity not found",
+            ErrorKind::AlreadyExists => "entity already exists",
+            ErrorKind::PermissionDenied => "permission denied",
+            ErrorKind::OutOfSpace => "not enough free space",
+            ErrorKind::TargetLocked => "target is locked",
+            ErrorKind::DirectoryNotEmpty => "directory not empty",
+            ErrorKind::InvalidPath => "invalid path",
+            ErrorKind::NotMounted => "not mounted",
+            ErrorKind::StorageAccessFailed => "storage access failed",
+            ErrorKind::DataCorrupted => "data corrupted",
+            ErrorKind::OutOfMemory => "out of memory",
+            ErrorKind::OutOfResource => "out of resources",
+            ErrorKind::Unsupported => "unsupported operation",
+            ErrorKind::InvalidInput => "invalid input parameter",
+            ErrorKind::InvalidData => "invalid data",
+            ErrorKind::UnexpectedEof => "unexpected end of file",
+            ErrorKind::WriteZero => "write zero",
+            ErrorKind::Other => "other error"
+        }
+    }
+}
+
+impl Error {
+    pub const fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            result: None
+        }
+    }
+
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    // The SDK result this error was created from, if any
+    pub const fn result(&self) -> Option<NxResult> {
+        self.result
+    }
+}
+
+impl From<NxResult> for Error {
+    fn from(result: NxResult) -> Self {
+        Self {
+            kind: ErrorKind::from_result(result),
+            result: Some(result)
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.result {
+            Some(result) => write!(f, "{} ({})", self.kind, result),
+            None => write!(f, "{}", self.kind)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::result::*;
+    use super::{Error, ErrorKind};
+
+    #[test]
+    fn kind_of_every_fs_result() {
+        let expected = [
+            (PATH_NOT_FOUND, ErrorKind::NotFound),
+            (PATH_ALREADY_EXISTS, ErrorKind::AlreadyExists),
+            (TARGET_LOCKED, ErrorKind::TargetLocked),
+            (DIRECTORY_NOT_EMPTY, ErrorKind::DirectoryNotEmpty),
+            (DIRECTORY_STATUS_CHANGED, ErrorKind::Other),
+            (USABLE_SPACE_NOT_ENOUGH, ErrorKind::OutOfSpace),
+            (UNSUPPORTED_SDK_VERSION, ErrorKind::Other),
+            (MOUNT_NAME_ALREADY_EXISTS, ErrorKind::AlreadyExists),
+            (TARGET_NOT_FOUND, ErrorKind::NotFound),
+            (SD_CARD_ACCESS_FAILED, ErrorKind::StorageAccessFailed),
+            (SD_CARD_NOT_PRESENT, ErrorKind::StorageAccessFailed),
+            (GAME_CARD_ACCESS_FAILED, ErrorKind::StorageAccessFailed),
+            (NOT_IMPLEMENTED, ErrorKind::Unsupported),
+            (OUT_OF_RANGE, ErrorKind::Other),
+            (ALLOCATION_MEMORY_FAILED, ErrorKind::OutOfMemory),
+            (DATA_CORRUPTED, ErrorKind::DataCorrupted),
+            (UNEXPECTED, ErrorKind::Other),
+            (PRECONDITION_VIOLATION, ErrorKind::InvalidInput),
+            (INVALID_ARGUMENT, ErrorKind::InvalidInput),
+            (INVALID_PATH, ErrorKind::InvalidPath),
+            (TOO_LONG_PATH, ErrorKind::InvalidPath),
+            (INVALID_CHARACTER, ErrorKind::InvalidPath),
+            (INVALID_PATH_FORMAT, ErrorKind::InvalidPath),
+            (DIRECTORY_UNOBTAINABLE, ErrorKind::InvalidPath),
+            (NOT_NORMALIZED, ErrorKind::InvalidPath),
+            (DIRECTORY_NOT_DELETABLE, ErrorKind::InvalidPath),
+            (DIRECTORY_NOT_RENAMABLE, ErrorKind::InvalidPath),
+            (INCOMPATIBLE_PATH, ErrorKind::InvalidPath),
+            (RENAME_TO_OTHER_FILE_SYSTEM, ErrorKind::InvalidPath),
+            (INVALID_OFFSET, ErrorKind::InvalidInput),
+            (INVALID_SIZE, ErrorKind::InvalidInput),
+            (NULLPTR_ARGUMENT, ErrorKind::InvalidInput),
+            (INVALID_MOUNT_NAME, ErrorKind::InvalidPath),
+            (INVALID_OPEN_MODE, ErrorKind::InvalidInput),
+            (FILE_EXTENSION_WITHOUT_OPEN_MODE_ALLOW_APPEND, ErrorKind::PermissionDenied),
+            (READ_NOT_PERMITTED, ErrorKind::PermissionDenied),
+            (WRITE_NOT_PERMITTED, ErrorKind::PermissionDenied),
+            (UNSUPPORTED_OPERATION, ErrorKind::Unsupported),
+            (PERMISSION_DENIED, ErrorKind::PermissionDenied),
+            (NEED_FLUSH, ErrorKind::Other),
+            (FILE_NOT_CLOSED, ErrorKind::Other),
+            (DIRECTORY_NOT_CLOSED, ErrorKind::Other),
+            (WRITE_MODE_FILE_NOT_CLOSED, ErrorKind::Other),
+            (OPEN_COUNT_LIMIT, ErrorKind::OutOfResource),
+            (NOT_MOUNTED, ErrorKind::NotMounted)
+        ];
+        for (result, kind) in expected.iter() {
+            assert_eq!(ErrorKind::from_result(*result), *kind, "{:?}", result);
+            let error = Error::from(*result);
+            assert_eq!(error.kind(), *kind);
+            assert_eq!(error.result(), Some(*result));
+        }
+    }
+
+    #[test]
+    fn results_from_other_modules() {
+        let os_out_of_memory = super::super::super::Result::new(3, 8);
+        assert_eq!(ErrorKind::from_result(os_out_of_memory), ErrorKind::Other);
+        // Same description as fs's PathNotFound, different module
+        assert_eq!(ErrorKind::from_result(super::super::super::Result::new(1, 1)), ErrorKind::Other);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(alloc::format!("{}", Error::from(PATH_NOT_FOUND)), "entity not found (2002-0001)");
+        assert_eq!(alloc::format!("{}", Error::new(ErrorKind::WriteZero)), "write zero");
+    }
+}
@@ -0,0 +1,192 @@
+use alloc::vec::Vec;
+use super::super::get_rust_result;
+use super::super::io::{self, SeekFrom};
+use super::{fs_impl, Error, FileHandle, OpenMode, WriteOptions};
+
+// Owned file handle which keeps track of its own cursor and closes the underlying FileHandle when dropped
+pub struct File {
+    handle: FileHandle,
+    position: u64
+}
+
+impl File {
+    #[dev_inline]
+    pub fn open<S: AsRef<str>>(path: S, mode: OpenMode) -> Result<Self, Error> {
+        super::open_file(path, mode).map(Self::from_handle)
+    }
+
+    pub const fn from_handle(handle: FileHandle) -> Self {
+        Self {
+            handle,
+            position: 0
+        }
+    }
+
+    pub const fn handle(&self) -> FileHandle {
+        self.handle
+    }
+
+    pub fn into_handle(self) -> FileHandle {
+        let handle = self.handle;
+        core::mem::forget(self);
+        handle
+    }
+
+    pub const fn position(&self) -> u64 {
+        self.position
+    }
+
+    #[dev_inline]
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        unsafe {
+            let mut read_bytes = 0;
+            let result = fs_impl::ReadFile(&mut read_bytes, self.handle, self.position as isize, buffer.as_mut_ptr() as _, buffer.len());
+            if result.is_success() {
+                self.position += read_bytes as u64;
+            }
+            get_rust_result!(result, read_bytes).map_err(Error::from)
+        }
+    }
+
+    #[dev_inline]
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize, Error> {
+        self.write_with_options(buffer, WriteOptions::empty())
+    }
+
+    #[dev_inline]
+    pub fn write_with_options(&mut self, buffer: &[u8], options: WriteOptions) -> Result<usize, Error> {
+        unsafe {
+            let result = fs_impl::WriteFile(self.handle, self.position as isize, buffer.as_ptr() as _, buffer.len(), &options);
+            if result.is_success() {
+                self.position += buffer.len() as u64;
+            }
+            get_rust_result!(result, buffer.len()).map_err(Error::from)
+        }
+    }
+
+    #[dev_inline]
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (offset, 0),
+            SeekFrom::End(offset) => (self.len()?, offset),
+            SeekFrom::Current(offset) => (self.position, offset)
+        };
+
+        let new_position = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+
+        // The SDK takes signed offsets, anything past i64::MAX would wrap around
+        match new_position {
+            Some(position) if position <= i64::MAX as u64 => {
+                self.position = position;
+                Ok(position)
+            },
+            _ => Err(super::result::INVALID_OFFSET.into())
+        }
+    }
+
+    #[dev_inline]
+    pub fn flush(&mut self) -> Result<(), Error> {
+        unsafe {
+            let result = fs_impl::FlushFile(self.handle);
+            get_rust_result!(result, ()).map_err(Error::from)
+        }
+    }
+
+    #[dev_inline]
+    pub fn set_len(&mut self, size: u64) -> Result<(), Error> {
+        unsafe {
+            let result = fs_impl::SetFileSize(self.handle, size as isize);
+            get_rust_result!(result, ()).map_err(Error::from)
+        }
+    }
+
+    #[dev_inline]
+    pub fn len(&self) -> Result<u64, Error> {
+        unsafe {
+            let mut size = 0;
+            let result = fs_impl::GetFileSize(&mut size, self.handle);
+            get_rust_result!(result, size as u64).map_err(Error::from)
+        }
+    }
+
+    #[dev_inline]
+    pub fn open_mode(&self) -> OpenMode {
+        unsafe {
+            fs_impl::GetFileOpenMode(self.handle)
+        }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe {
+            // nn::fs aborts when closing a writable file that has not been flushed
+            if fs_impl::GetFileOpenMode(self.handle).contains(OpenMode::WRITE) {
+                let _ = fs_impl::FlushFile(self.handle);
+            }
+            fs_impl::CloseFile(self.handle)
+        }
+    }
+}
+
+impl io::Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        File::read(self, buf)
+    }
+
+    // The size is only a hint, keeps reading until ReadFile returns 0 in case the file was read short or has grown
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let remaining = self.len()?.saturating_sub(self.position) as usize;
+        let start = buf.len();
+        buf.reserve(remaining);
+        loop {
+            let filled = buf.len();
+            if filled == buf.capacity() {
+                // Probe on the stack first so a file of exactly the expected size doesn't grow the buffer
+                let mut probe = [0u8; 32];
+                match File::read(self, &mut probe)? {
+                    0 => return Ok(filled - start),
+                    n => buf.extend_from_slice(&probe[..n])
+                }
+                continue;
+            }
+            let capacity = buf.capacity();
+            buf.resize(capacity, 0);
+            match File::read(self, &mut buf[filled..]) {
+                Ok(0) => {
+                    buf.truncate(filled);
+                    return Ok(filled - start);
+                },
+                Ok(n) => buf.truncate(filled + n),
+                Err(e) => {
+                    buf.truncate(filled);
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+impl io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        File::write(self, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        File::flush(self)
+    }
+
+    fn write_all_and_flush(&mut self, buf: &[u8]) -> Option<io::Result<()>> {
+        Some(self.write_with_options(buf, WriteOptions::FLUSH).map(|_| ()))
+    }
+}
+
+impl io::Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        File::seek(self, pos)
+    }
+}
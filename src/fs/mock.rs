@@ -0,0 +1,875 @@
+// Host implementation of the nn::fs symbols, enabled with the mock_fs feature.
+// Mount names are mapped onto host directories so code using nn::fs can be tested off-console,
+// and failures are reported with the same nn::Result codes the SDK would return.
+#![allow(non_snake_case)]
+
+use std::collections::HashMap;
+use std::fs as host_fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf as HostPathBuf;
+use std::string::{String, ToString};
+use std::sync::Mutex;
+use std::vec::Vec;
+
+use libc::*;
+use super::super::Result;
+use super::{result, AllocatorFunction, DeallocatorFunction, DirectoryEntry, DirectoryEntryType, DirectoryHandle, FileHandle, OpenDirectoryMode, OpenMode, Path, WriteOptions};
+use super::{ENTRY_NAME_BYTE_LENGTH_MAX, MOUNT_NAME_LENGTH_MAX};
+
+// Size reported by QueryMountRomCacheSize, MountRom only checks the buffer it is given is at least this big
+const ROM_CACHE_SIZE: usize = 0x4000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Device {
+    SdCard,
+    Rom,
+    SaveData
+}
+
+struct Mount {
+    device: Device,
+    root: HostPathBuf
+}
+
+struct OpenFile {
+    file: host_fs::File,
+    host_path: HostPathBuf,
+    mode: OpenMode,
+    // Writes that haven't been flushed yet, the SDK aborts if a file is closed in this state
+    dirty: bool
+}
+
+struct OpenDirectory {
+    entries: Vec<DirectoryEntry>,
+    position: usize
+}
+
+struct State {
+    devices: HashMap<Device, HostPathBuf>,
+    mounts: HashMap<String, Mount>,
+    files: HashMap<u64, OpenFile>,
+    directories: HashMap<u64, OpenDirectory>,
+    next_handle: u64
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+fn with_state<T, F: FnOnce(&mut State) -> T>(f: F) -> T {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let state = state.get_or_insert_with(|| State {
+        devices: HashMap::new(),
+        mounts: HashMap::new(),
+        files: HashMap::new(),
+        directories: HashMap::new(),
+        next_handle: 1
+    });
+    f(state)
+}
+
+fn to_result(result: core::result::Result<(), Result>) -> Result {
+    match result {
+        Ok(()) => Result::SUCCESS,
+        Err(e) => e
+    }
+}
+
+// Backs a device with a host directory, it has to be set before the matching mount function is called
+pub fn set_host_directory<P: Into<HostPathBuf>>(device: Device, path: P) {
+    with_state(|state| {
+        state.devices.insert(device, path.into());
+    })
+}
+
+// Forgets every mount, handle and host directory, meant to be called between tests
+pub fn reset() {
+    *STATE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+// Goes by ErrorKind, libc's errno values are the console's and don't line up with the host's
+fn host_error(error: std::io::Error) -> Result {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => result::PATH_NOT_FOUND,
+        std::io::ErrorKind::AlreadyExists => result::PATH_ALREADY_EXISTS,
+        std::io::ErrorKind::PermissionDenied => result::PERMISSION_DENIED,
+        std::io::ErrorKind::DirectoryNotEmpty => result::DIRECTORY_NOT_EMPTY,
+        std::io::ErrorKind::StorageFull => result::USABLE_SPACE_NOT_ENOUGH,
+        _ => result::UNEXPECTED
+    }
+}
+
+unsafe fn read_c_str<'a>(ptr: *const c_char) -> core::result::Result<&'a str, Result> {
+    if ptr.is_null() {
+        return Err(result::NULLPTR_ARGUMENT);
+    }
+    let bytes = core::slice::from_raw_parts(ptr.cast::<u8>(), strlen(ptr));
+    core::str::from_utf8(bytes).map_err(|_| result::INVALID_CHARACTER)
+}
+
+fn validate_mount_name(name: &str) -> core::result::Result<(), Result> {
+    if name.is_empty() || name.len() > MOUNT_NAME_LENGTH_MAX || name.contains([':', '/']) {
+        Err(result::INVALID_MOUNT_NAME)
+    } else {
+        Ok(())
+    }
+}
+
+struct Resolved {
+    mount: String,
+    device: Device,
+    host_path: HostPathBuf,
+    is_root: bool
+}
+
+impl State {
+    fn resolve(&self, path: &str) -> core::result::Result<Resolved, Result> {
+        let path = Path::new(path);
+        if path.without_mount().as_str().len() > ENTRY_NAME_BYTE_LENGTH_MAX {
+            return Err(result::TOO_LONG_PATH);
+        }
+        let mount_name = path.mount_name().ok_or(result::INVALID_PATH_FORMAT)?;
+        if !path.has_root() {
+            return Err(result::INVALID_PATH_FORMAT);
+        }
+        let mount = self.mounts.get(mount_name).ok_or(result::NOT_MOUNTED)?;
+
+        // normalize keeps ".." from climbing out of the mount
+        let normalized = path.normalize();
+        let relative = normalized.without_mount().as_str().trim_start_matches('/');
+        let mut host_path = mount.root.clone();
+        if !relative.is_empty() {
+            host_path.push(relative);
+        }
+        Ok(Resolved {
+            mount: String::from(mount_name),
+            device: mount.device,
+            host_path,
+            is_root: relative.is_empty()
+        })
+    }
+
+    fn resolve_writable(&self, path: &str) -> core::result::Result<Resolved, Result> {
+        let resolved = self.resolve(path)?;
+        if resolved.device == Device::Rom {
+            Err(result::UNSUPPORTED_OPERATION)
+        } else {
+            Ok(resolved)
+        }
+    }
+
+    fn is_open(&self, host_path: &HostPathBuf) -> bool {
+        self.files.values().any(|file| file.host_path == *host_path)
+    }
+
+    fn next_handle(&mut self) -> u64 {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        handle
+    }
+
+    fn file(&mut self, handle: FileHandle) -> &mut OpenFile {
+        match self.files.get_mut(&handle.0) {
+            Some(file) => file,
+            None => panic!("nn::fs: invalid file handle {:#x}", handle.0)
+        }
+    }
+
+    fn mount(&mut self, name: &str, device: Device, missing: Result) -> core::result::Result<(), Result> {
+        validate_mount_name(name)?;
+        if self.mounts.contains_key(name) {
+            return Err(result::MOUNT_NAME_ALREADY_EXISTS);
+        }
+        let root = self.devices.get(&device).cloned().ok_or(missing)?;
+        if !root.is_dir() {
+            return Err(missing);
+        }
+        self.mounts.insert(String::from(name), Mount { device, root });
+        Ok(())
+    }
+}
+
+fn parent_exists(resolved: &Resolved) -> core::result::Result<(), Result> {
+    match resolved.host_path.parent() {
+        Some(parent) if parent.is_dir() => Ok(()),
+        _ => Err(result::PATH_NOT_FOUND)
+    }
+}
+
+fn entry_type_of(host_path: &HostPathBuf) -> Option<DirectoryEntryType> {
+    let metadata = host_fs::metadata(host_path).ok()?;
+    if metadata.is_dir() {
+        Some(DirectoryEntryType::Directory)
+    } else {
+        Some(DirectoryEntryType::File)
+    }
+}
+
+// General
+
+/// # Safety
+/// None beyond the SDK function it stands in for, it is only unsafe to match its declaration.
+pub unsafe fn SetAllocator(_allocator: AllocatorFunction, _deallocator: DeallocatorFunction) {
+    // Host allocations never go through the SDK's allocator
+}
+
+// Mounting
+
+/// # Safety
+/// `name` must be null or point to a nul-terminated string.
+pub unsafe fn MountSaveDataForDebug(name: *const c_char) -> Result {
+    to_result(read_c_str(name).and_then(|name| with_state(|state| state.mount(name, Device::SaveData, result::TARGET_NOT_FOUND))))
+}
+
+/// # Safety
+/// `name` must be null or point to a nul-terminated string.
+pub unsafe fn MountSdCardForDebug(name: *const c_char) -> Result {
+    to_result(read_c_str(name).and_then(|name| with_state(|state| state.mount(name, Device::SdCard, result::SD_CARD_NOT_PRESENT))))
+}
+
+/// # Safety
+/// `out` must be valid for writes.
+pub unsafe fn QueryMountRomCacheSize(out: *mut usize) -> Result {
+    *out = ROM_CACHE_SIZE;
+    Result::SUCCESS
+}
+
+/// # Safety
+/// `name` must be null or point to a nul-terminated string. `buffer` must be valid for writes of `buffer_size` bytes.
+pub unsafe fn MountRom(name: *const c_char, buffer: *mut c_void, buffer_size: usize) -> Result {
+    if buffer.is_null() {
+        return result::NULLPTR_ARGUMENT;
+    }
+    if buffer_size < ROM_CACHE_SIZE {
+        return result::ALLOCATION_MEMORY_FAILED;
+    }
+    to_result(read_c_str(name).and_then(|name| with_state(|state| state.mount(name, Device::Rom, result::TARGET_NOT_FOUND))))
+}
+
+/// # Safety
+/// `name` must be null or point to a nul-terminated string.
+pub unsafe fn Unmount(name: *const c_char) {
+    let name = read_c_str(name).unwrap_or("");
+    with_state(|state| {
+        if state.mounts.remove(name).is_none() {
+            panic!("nn::fs::Unmount: \"{}\" is not mounted", name);
+        }
+    })
+}
+
+// File Operations
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string.
+pub unsafe fn CreateFile(path: *const c_char, size: isize) -> Result {
+    to_result(read_c_str(path).and_then(|path| with_state(|state| {
+        let resolved = state.resolve_writable(path)?;
+        if size < 0 {
+            return Err(result::OUT_OF_RANGE);
+        }
+        parent_exists(&resolved)?;
+        if resolved.host_path.exists() {
+            return Err(result::PATH_ALREADY_EXISTS);
+        }
+        let file = host_fs::File::create(&resolved.host_path).map_err(host_error)?;
+        file.set_len(size as u64).map_err(host_error)
+    })))
+}
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string.
+pub unsafe fn DeleteFile(path: *const c_char) -> Result {
+    to_result(read_c_str(path).and_then(|path| with_state(|state| {
+        let resolved = state.resolve_writable(path)?;
+        if entry_type_of(&resolved.host_path) != Some(DirectoryEntryType::File) {
+            return Err(result::PATH_NOT_FOUND);
+        }
+        if state.is_open(&resolved.host_path) {
+            return Err(result::TARGET_LOCKED);
+        }
+        host_fs::remove_file(&resolved.host_path).map_err(host_error)
+    })))
+}
+
+unsafe fn rename(current: *const c_char, new: *const c_char, entry_type: DirectoryEntryType) -> core::result::Result<(), Result> {
+    let current = read_c_str(current)?;
+    let new = read_c_str(new)?;
+    with_state(|state| {
+        let from = state.resolve_writable(current)?;
+        let to = state.resolve_writable(new)?;
+        if from.mount != to.mount {
+            return Err(result::RENAME_TO_OTHER_FILE_SYSTEM);
+        }
+        if entry_type_of(&from.host_path) != Some(entry_type) {
+            return Err(result::PATH_NOT_FOUND);
+        }
+        parent_exists(&to)?;
+        if to.host_path.exists() {
+            return Err(result::PATH_ALREADY_EXISTS);
+        }
+        if entry_type == DirectoryEntryType::Directory {
+            if from.is_root {
+                return Err(result::DIRECTORY_NOT_RENAMABLE);
+            }
+            if to.host_path.starts_with(&from.host_path) {
+                return Err(result::DIRECTORY_NOT_RENAMABLE);
+            }
+            if state.files.values().any(|file| file.host_path.starts_with(&from.host_path)) {
+                return Err(result::TARGET_LOCKED);
+            }
+        } else if state.is_open(&from.host_path) {
+            return Err(result::TARGET_LOCKED);
+        }
+        host_fs::rename(&from.host_path, &to.host_path).map_err(host_error)
+    })
+}
+
+/// # Safety
+/// `current` and `new` must each be null or point to a nul-terminated string.
+pub unsafe fn RenameFile(current: *const c_char, new: *const c_char) -> Result {
+    to_result(rename(current, new, DirectoryEntryType::File))
+}
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string. `out` must be valid for writes.
+pub unsafe fn OpenFile(out: *mut FileHandle, path: *const c_char, mode: OpenMode) -> Result {
+    to_result(read_c_str(path).and_then(|path| with_state(|state| {
+        if !mode.intersects(OpenMode::READ | OpenMode::WRITE) {
+            return Err(result::INVALID_OPEN_MODE);
+        }
+        let resolved = if mode.contains(OpenMode::WRITE) {
+            state.resolve_writable(path)?
+        } else {
+            state.resolve(path)?
+        };
+        if entry_type_of(&resolved.host_path) != Some(DirectoryEntryType::File) {
+            return Err(result::PATH_NOT_FOUND);
+        }
+        // Only one writer is allowed at a time, and nobody may write a file that is open elsewhere
+        let conflict = state.files.values().any(|file| {
+            file.host_path == resolved.host_path && (mode.contains(OpenMode::WRITE) || file.mode.contains(OpenMode::WRITE))
+        });
+        if conflict {
+            return Err(result::TARGET_LOCKED);
+        }
+        let file = host_fs::OpenOptions::new()
+            .read(true)
+            .write(mode.contains(OpenMode::WRITE))
+            .open(&resolved.host_path)
+            .map_err(host_error)?;
+        let handle = state.next_handle();
+        state.files.insert(handle, OpenFile {
+            file,
+            host_path: resolved.host_path,
+            mode,
+            dirty: false
+        });
+        *out = FileHandle(handle);
+        Ok(())
+    })))
+}
+
+/// # Safety
+/// None beyond the SDK function it stands in for, it is only unsafe to match its declaration.
+pub unsafe fn CloseFile(handle: FileHandle) {
+    with_state(|state| {
+        let file = state.file(handle);
+        if file.dirty {
+            panic!("nn::fs::CloseFile: file was closed without being flushed ({})", result::WRITE_MODE_FILE_NOT_CLOSED);
+        }
+        state.files.remove(&handle.0);
+    })
+}
+
+unsafe fn read_at(handle: FileHandle, offset: isize, buffer: *mut c_void, buffer_size: usize) -> core::result::Result<usize, Result> {
+    with_state(|state| {
+        let file = state.file(handle);
+        if !file.mode.contains(OpenMode::READ) {
+            return Err(result::READ_NOT_PERMITTED);
+        }
+        if buffer.is_null() && buffer_size != 0 {
+            return Err(result::NULLPTR_ARGUMENT);
+        }
+        let len = file.file.metadata().map_err(host_error)?.len();
+        if offset < 0 || offset as u64 > len {
+            return Err(result::OUT_OF_RANGE);
+        }
+        let size = core::cmp::min(buffer_size as u64, len - offset as u64) as usize;
+        if size == 0 {
+            return Ok(0);
+        }
+        let buffer = core::slice::from_raw_parts_mut(buffer as *mut u8, size);
+        file.file.seek(SeekFrom::Start(offset as u64)).map_err(host_error)?;
+        file.file.read_exact(buffer).map_err(host_error)?;
+        Ok(size)
+    })
+}
+
+/// # Safety
+/// `buffer` must be valid for writes of `buffer_size` bytes.
+pub unsafe fn ReadFileFixedSize(handle: FileHandle, offset: isize, buffer: *mut c_void, buffer_size: usize) -> Result {
+    match read_at(handle, offset, buffer, buffer_size) {
+        Ok(read) if read == buffer_size => Result::SUCCESS,
+        Ok(_) => result::OUT_OF_RANGE,
+        Err(e) => e
+    }
+}
+
+/// # Safety
+/// `read_size` must be valid for writes. `buffer` must be valid for writes of `buffer_size` bytes.
+pub unsafe fn ReadFile(read_size: *mut usize, handle: FileHandle, offset: isize, buffer: *mut c_void, buffer_size: usize) -> Result {
+    match read_at(handle, offset, buffer, buffer_size) {
+        Ok(read) => {
+            *read_size = read;
+            Result::SUCCESS
+        },
+        Err(e) => e
+    }
+}
+
+/// # Safety
+/// `options` must be valid for reads. `buffer` must be valid for reads of `buffer_size` bytes.
+pub unsafe fn WriteFile(handle: FileHandle, offset: isize, buffer: *const c_void, buffer_size: usize, options: *const WriteOptions) -> Result {
+    to_result(with_state(|state| {
+        let file = state.file(handle);
+        if !file.mode.contains(OpenMode::WRITE) {
+            return Err(result::WRITE_NOT_PERMITTED);
+        }
+        if buffer.is_null() && buffer_size != 0 {
+            return Err(result::NULLPTR_ARGUMENT);
+        }
+        if offset < 0 {
+            return Err(result::OUT_OF_RANGE);
+        }
+        let len = file.file.metadata().map_err(host_error)?.len();
+        if offset as u64 + buffer_size as u64 > len && !file.mode.contains(OpenMode::ALLOW_APPEND) {
+            return Err(result::FILE_EXTENSION_WITHOUT_OPEN_MODE_ALLOW_APPEND);
+        }
+        if buffer_size != 0 {
+            let buffer = core::slice::from_raw_parts(buffer as *const u8, buffer_size);
+            file.file.seek(SeekFrom::Start(offset as u64)).map_err(host_error)?;
+            file.file.write_all(buffer).map_err(host_error)?;
+            file.dirty = true;
+        }
+        if !options.is_null() && (*options).contains(WriteOptions::FLUSH) {
+            file.file.flush().map_err(host_error)?;
+            file.dirty = false;
+        }
+        Ok(())
+    }))
+}
+
+/// # Safety
+/// None beyond the SDK function it stands in for, it is only unsafe to match its declaration.
+pub unsafe fn FlushFile(handle: FileHandle) -> Result {
+    to_result(with_state(|state| {
+        let file = state.file(handle);
+        file.file.flush().map_err(host_error)?;
+        file.dirty = false;
+        Ok(())
+    }))
+}
+
+/// # Safety
+/// None beyond the SDK function it stands in for, it is only unsafe to match its declaration.
+pub unsafe fn SetFileSize(handle: FileHandle, size: isize) -> Result {
+    to_result(with_state(|state| {
+        let file = state.file(handle);
+        if !file.mode.contains(OpenMode::WRITE) {
+            return Err(result::WRITE_NOT_PERMITTED);
+        }
+        if size < 0 {
+            return Err(result::OUT_OF_RANGE);
+        }
+        file.file.set_len(size as u64).map_err(host_error)?;
+        file.dirty = true;
+        Ok(())
+    }))
+}
+
+/// # Safety
+/// `out` must be valid for writes.
+pub unsafe fn GetFileSize(out: *mut isize, handle: FileHandle) -> Result {
+    to_result(with_state(|state| {
+        let file = state.file(handle);
+        *out = file.file.metadata().map_err(host_error)?.len() as isize;
+        Ok(())
+    }))
+}
+
+/// # Safety
+/// None beyond the SDK function it stands in for, it is only unsafe to match its declaration.
+pub unsafe fn GetFileOpenMode(handle: FileHandle) -> OpenMode {
+    with_state(|state| state.file(handle).mode)
+}
+
+// Directory Utils
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string.
+pub unsafe fn CreateDirectory(path: *const c_char) -> Result {
+    to_result(read_c_str(path).and_then(|path| with_state(|state| {
+        let resolved = state.resolve_writable(path)?;
+        parent_exists(&resolved)?;
+        if resolved.host_path.exists() {
+            return Err(result::PATH_ALREADY_EXISTS);
+        }
+        host_fs::create_dir(&resolved.host_path).map_err(host_error)
+    })))
+}
+
+unsafe fn delete_directory(path: *const c_char, recursive: bool) -> core::result::Result<(), Result> {
+    let path = read_c_str(path)?;
+    with_state(|state| {
+        let resolved = state.resolve_writable(path)?;
+        if entry_type_of(&resolved.host_path) != Some(DirectoryEntryType::Directory) {
+            return Err(result::PATH_NOT_FOUND);
+        }
+        if resolved.is_root {
+            return Err(result::DIRECTORY_NOT_DELETABLE);
+        }
+        if recursive {
+            if state.files.values().any(|file| file.host_path.starts_with(&resolved.host_path)) {
+                return Err(result::TARGET_LOCKED);
+            }
+            host_fs::remove_dir_all(&resolved.host_path).map_err(host_error)
+        } else {
+            host_fs::remove_dir(&resolved.host_path).map_err(host_error)
+        }
+    })
+}
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string.
+pub unsafe fn DeleteDirectory(path: *const c_char) -> Result {
+    to_result(delete_directory(path, false))
+}
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string.
+pub unsafe fn DeleteDirectoryRecursively(path: *const c_char) -> Result {
+    to_result(delete_directory(path, true))
+}
+
+/// # Safety
+/// `current` and `new` must each be null or point to a nul-terminated string.
+pub unsafe fn RenameDirectory(current: *const c_char, new: *const c_char) -> Result {
+    to_result(rename(current, new, DirectoryEntryType::Directory))
+}
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string. `out` must be valid for writes.
+pub unsafe fn GetEntryType(out: *mut DirectoryEntryType, path: *const c_char) -> Result {
+    to_result(read_c_str(path).and_then(|path| with_state(|state| {
+        let resolved = state.resolve(path)?;
+        *out = entry_type_of(&resolved.host_path).ok_or(result::PATH_NOT_FOUND)?;
+        Ok(())
+    })))
+}
+
+fn make_entry(name: &str, entry_type: DirectoryEntryType, size: u64) -> DirectoryEntry {
+    let mut entry: DirectoryEntry = unsafe { core::mem::zeroed() };
+    let len = core::cmp::min(name.len(), ENTRY_NAME_BYTE_LENGTH_MAX);
+    for (dst, src) in entry.name.iter_mut().zip(name.as_bytes()[..len].iter()) {
+        *dst = *src as c_char;
+    }
+    entry.entry_type = entry_type;
+    entry.size = size as isize;
+    entry
+}
+
+/// # Safety
+/// `path` must be null or point to a nul-terminated string. `out_handle` must be valid for writes.
+pub unsafe fn OpenDirectory(out_handle: *mut DirectoryHandle, path: *const c_char, mode: OpenDirectoryMode) -> Result {
+    to_result(read_c_str(path).and_then(|path| with_state(|state| {
+        let resolved = state.resolve(path)?;
+        if entry_type_of(&resolved.host_path) != Some(DirectoryEntryType::Directory) {
+            return Err(result::PATH_NOT_FOUND);
+        }
+        let mut entries = Vec::new();
+        for host_entry in host_fs::read_dir(&resolved.host_path).map_err(host_error)? {
+            let host_entry = host_entry.map_err(host_error)?;
+            let metadata = host_entry.metadata().map_err(host_error)?;
+            let name = host_entry.file_name().to_string_lossy().to_string();
+            if metadata.is_dir() && mode.contains(OpenDirectoryMode::DIRECTORY) {
+                entries.push(make_entry(&name, DirectoryEntryType::Directory, 0));
+            } else if metadata.is_file() && mode.contains(OpenDirectoryMode::FILE) {
+                entries.push(make_entry(&name, DirectoryEntryType::File, metadata.len()));
+            }
+        }
+        let handle = state.next_handle();
+        state.directories.insert(handle, OpenDirectory {
+            entries,
+            position: 0
+        });
+        *out_handle = DirectoryHandle(handle);
+        Ok(())
+    })))
+}
+
+/// # Safety
+/// `stored_entries` must be valid for writes. `entry_buffer` must be valid for writes of `buffer_count` entries.
+pub unsafe fn ReadDirectory(stored_entries: *mut isize, entry_buffer: *mut DirectoryEntry, handle: DirectoryHandle, buffer_count: isize) -> Result {
+    with_state(|state| {
+        let directory = match state.directories.get_mut(&handle.0) {
+            Some(directory) => directory,
+            None => panic!("nn::fs: invalid directory handle {:#x}", handle.0)
+        };
+        let remaining = &directory.entries[directory.position..];
+        let count = core::cmp::min(remaining.len(), buffer_count.max(0) as usize);
+        for (i, entry) in remaining[..count].iter().enumerate() {
+            entry_buffer.add(i).write(entry.clone());
+        }
+        directory.position += count;
+        *stored_entries = count as isize;
+        Result::SUCCESS
+    })
+}
+
+/// # Safety
+/// `out_count` must be valid for writes.
+pub unsafe fn GetDirectoryEntryCount(out_count: *mut isize, handle: DirectoryHandle) -> Result {
+    with_state(|state| {
+        match state.directories.get(&handle.0) {
+            Some(directory) => *out_count = directory.entries.len() as isize,
+            None => panic!("nn::fs: invalid directory handle {:#x}", handle.0)
+        }
+        Result::SUCCESS
+    })
+}
+
+/// # Safety
+/// None beyond the SDK function it stands in for, it is only unsafe to match its declaration.
+pub unsafe fn CloseDirectory(handle: DirectoryHandle) {
+    with_state(|state| {
+        if state.directories.remove(&handle.0).is_none() {
+            panic!("nn::fs: invalid directory handle {:#x}", handle.0);
+        }
+    })
+}
+
+#[cfg(all(test, feature = "mock_fs"))]
+mod tests {
+    use std::format;
+    use std::string::String;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Mutex, MutexGuard};
+    use std::vec::Vec;
+    use super::super::super::io::{Read, Seek, SeekFrom, Write};
+    use super::super::{self as fs, ErrorKind, File, OpenMode};
+    use super::{host_error, result, set_host_directory, Device, HostPathBuf};
+
+    // The mock's state is global, so tests touching it take turns
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    // Mounts a fresh host directory as "sd:" and another one as "save:", both removed again on drop
+    struct Fixture {
+        dirs: [HostPathBuf; 2],
+        _guard: MutexGuard<'static, ()>
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            static NEXT: AtomicUsize = AtomicUsize::new(0);
+            let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let id = NEXT.fetch_add(1, Ordering::Relaxed);
+            let base = std::env::temp_dir().join(format!("nn-mock-fs-{}-{}", std::process::id(), id));
+            let dirs = [base.join("sd"), base.join("save")];
+            for dir in dirs.iter() {
+                std::fs::create_dir_all(dir).unwrap();
+            }
+            super::reset();
+            set_host_directory(Device::SdCard, &dirs[0]);
+            set_host_directory(Device::SaveData, &dirs[1]);
+            fs::mount_sd_card("sd").unwrap();
+            fs::mount_save_data("save").unwrap();
+            Self {
+                dirs,
+                _guard: guard
+            }
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            super::reset();
+            let _ = std::fs::remove_dir_all(self.dirs[0].parent().unwrap());
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let fixture = Fixture::new();
+        fs::create_file("sd:/file.bin", 0).unwrap();
+        {
+            let mut file = File::open("sd:/file.bin", OpenMode::READ | OpenMode::WRITE | OpenMode::ALLOW_APPEND).unwrap();
+            file.write_all(b"hello world").unwrap();
+            file.flush().unwrap();
+            assert_eq!(file.len().unwrap(), 11);
+
+            let mut buffer = [0; 5];
+            assert_eq!(file.seek(SeekFrom::Start(0)).unwrap(), 0);
+            file.read_exact(&mut buffer).unwrap();
+            assert_eq!(&buffer, b"hello");
+            assert_eq!(file.seek(SeekFrom::End(-5)).unwrap(), 6);
+            file.read_exact(&mut buffer).unwrap();
+            assert_eq!(&buffer, b"world");
+            assert_eq!(file.seek(SeekFrom::Current(-11)).unwrap(), 0);
+            assert!(file.seek(SeekFrom::Current(-1)).is_err());
+            let error = file.seek(SeekFrom::Start(i64::MAX as u64 + 1)).unwrap_err();
+            assert_eq!(error.result(), Some(result::INVALID_OFFSET));
+            assert_eq!(file.position(), 0);
+
+            let mut contents = Vec::new();
+            assert_eq!(file.read_to_end(&mut contents).unwrap(), 11);
+            assert_eq!(contents, b"hello world");
+        }
+        assert_eq!(std::fs::read(fixture.dirs[0].join("file.bin")).unwrap(), b"hello world");
+
+        fs::write("sd:/text.txt", "some text").unwrap();
+        assert_eq!(fs::read_to_string("sd:/text.txt").unwrap(), "some text");
+        assert_eq!(fs::metadata("sd:/text.txt").unwrap().len(), 9);
+    }
+
+    #[test]
+    fn read_to_end_from_the_cursor() {
+        let _fixture = Fixture::new();
+        let contents: Vec<u8> = (0..0x3000).map(|i| i as u8).collect();
+        fs::write("sd:/big.bin", &contents).unwrap();
+        let mut file = File::open("sd:/big.bin", OpenMode::READ).unwrap();
+        file.seek(SeekFrom::Start(0x10)).unwrap();
+        let mut buffer = Vec::from(&b"prefix"[..]);
+        assert_eq!(file.read_to_end(&mut buffer).unwrap(), 0x3000 - 0x10);
+        assert_eq!(&buffer[..6], b"prefix");
+        assert_eq!(&buffer[6..], &contents[0x10..]);
+        assert_eq!(file.read_to_end(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_dir_in_batches() {
+        let _fixture = Fixture::new();
+        fs::create_directory("sd:/many").unwrap();
+        for i in 0..40 {
+            fs::write(format!("sd:/many/{:02}.txt", i), [i as u8]).unwrap();
+        }
+        fs::create_directory("sd:/many/sub").unwrap();
+
+        let mut names: Vec<String> = fs::read_dir("sd:/many").unwrap().map(|entry| String::from(entry.unwrap().name())).collect();
+        names.sort();
+        assert_eq!(names.len(), 41);
+        assert_eq!(names[0], "00.txt");
+        assert_eq!(names[39], "39.txt");
+        assert_eq!(names[40], "sub");
+        let dirs = fs::read_dir("sd:/many").unwrap().filter(|entry| entry.as_ref().unwrap().entry_type() == fs::DirectoryEntryType::Directory).count();
+        assert_eq!(dirs, 1);
+    }
+
+    #[test]
+    fn create_copy_and_move_dirs() {
+        let fixture = Fixture::new();
+        fs::create_dir_all("sd:/a/b/c").unwrap();
+        // Already there is fine
+        fs::create_dir_all("sd:/a/b/c/").unwrap();
+        fs::write("sd:/a/top.txt", "top").unwrap();
+        fs::write("sd:/a/b/c/deep.txt", "deep").unwrap();
+        assert!(fixture.dirs[0].join("a/b/c").is_dir());
+
+        fs::copy_dir_all("sd:/a", "sd:/copy").unwrap();
+        assert_eq!(fs::read_to_string("sd:/copy/top.txt").unwrap(), "top");
+        assert_eq!(fs::read_to_string("sd:/copy/b/c/deep.txt").unwrap(), "deep");
+        assert!(fs::exists("sd:/a/top.txt"));
+
+        // Spelled differently from what walk_dir yields
+        fs::copy_dir_all("sd://a//", "save:").unwrap();
+        assert_eq!(fs::read_to_string("save:/b/c/deep.txt").unwrap(), "deep");
+        fs::copy_dir_all("sd:", "save:/root").unwrap();
+        assert_eq!(fs::read_to_string("save:/root/a/top.txt").unwrap(), "top");
+
+        // Copying into itself would never finish
+        let error = fs::copy_dir_all("sd:/a/", "sd://a/./b/copy").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(!fs::exists("sd:/a/b/copy"));
+
+        // Within a mount this is a rename
+        fs::move_dir("sd:/copy", "sd:/moved").unwrap();
+        assert!(!fs::exists("sd:/copy"));
+        assert_eq!(fs::read_to_string("sd:/moved/b/c/deep.txt").unwrap(), "deep");
+
+        // Across mounts RenameDirectory fails, so it's copied and deleted
+        fs::move_dir("sd:/moved", "save:/moved").unwrap();
+        assert!(!fs::exists("sd:/moved"));
+        assert_eq!(fs::read_to_string("save:/moved/top.txt").unwrap(), "top");
+        assert_eq!(fs::read_to_string("save:/moved/b/c/deep.txt").unwrap(), "deep");
+
+        // Other rename errors aren't papered over with a copy
+        let error = fs::move_dir("sd:/missing", "save:/missing").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        let error = fs::move_dir("sd:/a", "sd:/a/b/inside").unwrap_err();
+        assert_eq!(error.result(), Some(result::DIRECTORY_NOT_RENAMABLE));
+        assert!(fs::exists("sd:/a/top.txt"));
+    }
+
+    #[test]
+    fn skip_current_dir() {
+        let _fixture = Fixture::new();
+        fs::create_dir_all("sd:/walk/a/inner").unwrap();
+        fs::create_dir_all("sd:/walk/b").unwrap();
+        fs::write("sd:/walk/a/inner/file.txt", "").unwrap();
+        fs::write("sd:/walk/b/1.txt", "").unwrap();
+        fs::write("sd:/walk/b/2.txt", "").unwrap();
+
+        let mut walk = fs::walk_dir("sd:/walk/").sort_by_name().into_iter();
+        let mut names = Vec::new();
+        while let Some(entry) = walk.next() {
+            let entry = entry.unwrap();
+            names.push(String::from(entry.name()));
+            if entry.name() == "a" || entry.name() == "1.txt" {
+                walk.skip_current_dir();
+            }
+        }
+        assert_eq!(names, ["walk", "a", "b", "1.txt"]);
+
+        // Contents first, skipping a directory that was already walked does nothing
+        let mut walk = fs::walk_dir("sd:/walk").sort_by_name().contents_first(true).into_iter();
+        let mut names = Vec::new();
+        while let Some(entry) = walk.next() {
+            let entry = entry.unwrap();
+            names.push(String::from(entry.name()));
+            if entry.name() == "a" {
+                walk.skip_current_dir();
+            }
+        }
+        assert_eq!(names, ["file.txt", "inner", "a", "1.txt", "2.txt", "b", "walk"]);
+    }
+
+    #[test]
+    fn error_codes() {
+        let _fixture = Fixture::new();
+        fs::create_directory("sd:/dir").unwrap();
+        fs::write("sd:/dir/file.txt", "").unwrap();
+
+        let error = File::open("sd:/missing.txt", OpenMode::READ).err().unwrap();
+        assert_eq!(error.result(), Some(result::PATH_NOT_FOUND));
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+
+        let error = fs::create_directory("sd:/dir").unwrap_err();
+        assert_eq!(error.result(), Some(result::PATH_ALREADY_EXISTS));
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+
+        let error = fs::delete_directory("sd:/dir", false).unwrap_err();
+        assert_eq!(error.result(), Some(result::DIRECTORY_NOT_EMPTY));
+        assert_eq!(error.kind(), ErrorKind::DirectoryNotEmpty);
+        fs::delete_directory("sd:/dir", true).unwrap();
+
+        let error = fs::create_directory("nope:/dir").unwrap_err();
+        assert_eq!(error.result(), Some(result::NOT_MOUNTED));
+    }
+
+    #[test]
+    fn host_errors() {
+        use std::io::{Error, ErrorKind as HostErrorKind};
+        assert_eq!(host_error(Error::from(HostErrorKind::NotFound)), result::PATH_NOT_FOUND);
+        assert_eq!(host_error(Error::from(HostErrorKind::AlreadyExists)), result::PATH_ALREADY_EXISTS);
+        assert_eq!(host_error(Error::from(HostErrorKind::DirectoryNotEmpty)), result::DIRECTORY_NOT_EMPTY);
+        assert_eq!(host_error(Error::from(HostErrorKind::StorageFull)), result::USABLE_SPACE_NOT_ENOUGH);
+        assert_eq!(host_error(Error::from(HostErrorKind::Interrupted)), result::UNEXPECTED);
+    }
+}
@@ -0,0 +1,190 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use super::super::io::{self, Read};
+use super::{Component, DirectoryEntryType, ErrorKind, File, OpenMode, Path, PathBuf, WriteOptions};
+
+// Chunk size used when copying between files, every chunk is a read and a write IPC round trip
+const COPY_CHUNK_SIZE: usize = 0x10000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    entry_type: DirectoryEntryType,
+    len: u64
+}
+
+impl Metadata {
+    pub const fn entry_type(&self) -> DirectoryEntryType {
+        self.entry_type
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == DirectoryEntryType::Directory
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.entry_type == DirectoryEntryType::File
+    }
+
+    // Always 0 for directories
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+#[dev_inline]
+pub fn read<S: AsRef<str>>(path: S) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path, OpenMode::READ)?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[dev_inline]
+pub fn read_to_string<S: AsRef<str>>(path: S) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|_| io::Error::new(io::ErrorKind::InvalidData))
+}
+
+// Opens a file for writing that is exactly `size` bytes long, creating it if it does not exist yet
+fn create_sized(path: &str, size: u64) -> io::Result<File> {
+    if exists(path) {
+        let mut file = File::open(path, OpenMode::WRITE)?;
+        file.set_len(size)?;
+        Ok(file)
+    } else {
+        super::create_file(path, size as isize)?;
+        Ok(File::open(path, OpenMode::WRITE)?)
+    }
+}
+
+#[dev_inline]
+pub fn write<S: AsRef<str>, C: AsRef<[u8]>>(path: S, contents: C) -> io::Result<()> {
+    let contents = contents.as_ref();
+    let mut file = create_sized(path.as_ref(), contents.len() as u64)?;
+    file.write_with_options(contents, WriteOptions::FLUSH)?;
+    Ok(())
+}
+
+#[dev_inline]
+pub fn copy<S: AsRef<str>, R: AsRef<str>>(from: S, to: R) -> io::Result<u64> {
+    let mut source = File::open(from, OpenMode::READ)?;
+    let len = source.len()?;
+    let mut dest = create_sized(to.as_ref(), len)?;
+
+    let mut buf = alloc::vec![0; core::cmp::min(len as usize, COPY_CHUNK_SIZE)];
+    let mut copied = 0;
+    while copied < len {
+        let read = source.read(&mut buf)?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof));
+        }
+        copied += read as u64;
+        let options = if copied >= len { WriteOptions::FLUSH } else { WriteOptions::empty() };
+        dest.write_with_options(&buf[..read], options)?;
+    }
+    Ok(copied)
+}
+
+#[dev_inline]
+pub fn exists<S: AsRef<str>>(path: S) -> bool {
+    super::get_entry_type(path).is_ok()
+}
+
+#[dev_inline]
+pub fn metadata<S: AsRef<str>>(path: S) -> io::Result<Metadata> {
+    let path = path.as_ref();
+    let entry_type = super::get_entry_type(path)?;
+    let len = match entry_type {
+        DirectoryEntryType::File => File::open(path, OpenMode::READ)?.len()?,
+        DirectoryEntryType::Directory => 0
+    };
+    Ok(Metadata {
+        entry_type,
+        len
+    })
+}
+
+// nn::fs::CreateDirectory fails if the directory is already there, which is fine for us as long as it is a directory
+fn ensure_directory(path: &str) -> io::Result<()> {
+    match super::create_directory(path) {
+        Ok(()) => Ok(()),
+        Err(e) => match super::get_entry_type(path) {
+            Ok(DirectoryEntryType::Directory) => Ok(()),
+            _ => Err(e)
+        }
+    }
+}
+
+// Creates a directory along with any missing parents
+#[dev_inline]
+pub fn create_dir_all<S: AsRef<str>>(path: S) -> io::Result<()> {
+    let path = Path::new(path.as_ref()).normalize();
+    let mut current = String::with_capacity(path.as_str().len());
+    for component in path.components() {
+        match component {
+            Component::Mount(name) => current.push_str(name),
+            Component::RootDir => current.push('/'),
+            Component::Normal(name) => {
+                if !current.is_empty() && !current.ends_with('/') {
+                    current.push('/');
+                }
+                current.push_str(name);
+                ensure_directory(&current)?;
+            },
+            // normalize only keeps leading ".." components of relative paths, which nn::fs can't resolve anyway
+            Component::CurDir | Component::ParentDir => {
+                current.push_str(component.as_str());
+                current.push('/');
+            }
+        }
+    }
+    Ok(())
+}
+
+// nn::fs only takes rooted paths, so a bare mount name such as "sd:" stands for its root
+fn normalize_dir(path: &str) -> PathBuf {
+    let mut path = Path::new(path).normalize();
+    if path.mount_name().is_some() && path.without_mount().as_str().is_empty() {
+        path.push("/");
+    }
+    path
+}
+
+// Recursively copies the contents of a directory, merging into `to` if it already exists
+#[dev_inline]
+pub fn copy_dir_all<S: AsRef<str>, R: AsRef<str>>(from: S, to: R) -> io::Result<()> {
+    let from = normalize_dir(from.as_ref());
+    let to = normalize_dir(to.as_ref());
+    // The walk would find the copy inside `from` and keep copying it into itself
+    if to.starts_with(&from) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput));
+    }
+    create_dir_all(&to)?;
+    for entry in super::walk_dir(from.as_str()).min_depth(1) {
+        let entry = entry?;
+        // Every path the walk yields is built on top of `from`
+        let relative = entry.path().strip_prefix(&from).unwrap();
+        let target = to.join(relative);
+        if entry.is_dir() {
+            ensure_directory(target.as_str())?;
+        } else {
+            copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+// Tries nn::fs::RenameDirectory first. When it can't move across file systems, the tree is copied and the original
+// deleted instead. Any other error from the rename is returned as is.
+#[dev_inline]
+pub fn move_dir<S: AsRef<str>, R: AsRef<str>>(from: S, to: R) -> io::Result<()> {
+    let from = Path::new(from.as_ref());
+    let to = Path::new(to.as_ref());
+    match super::rename_directory(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.result() == Some(super::result::RENAME_TO_OTHER_FILE_SYSTEM) || e.kind() == ErrorKind::Unsupported => {
+            copy_dir_all(from, to)?;
+            super::delete_directory(from, true)
+        },
+        Err(e) => Err(e)
+    }
+}
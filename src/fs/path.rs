@@ -0,0 +1,543 @@
+use alloc::borrow::{Borrow, ToOwned};
+use alloc::string::String;
+use core::fmt;
+use core::ops::Deref;
+use super::{Error, ENTRY_NAME_BYTE_LENGTH_MAX, MOUNT_NAME_LENGTH_MAX};
+
+// Borrowed nn::fs path such as "sd:/atmosphere/contents". Paths are always UTF-8 on the SDK side,
+// so unlike std::path this is a thin wrapper over str.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path {
+    inner: str
+}
+
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathBuf {
+    inner: String
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Component<'a> {
+    // The mount name with its ':', such as "sd:" or "rom:", so components can be joined back into the same path
+    Mount(&'a str),
+    RootDir,
+    CurDir,
+    ParentDir,
+    Normal(&'a str)
+}
+
+#[derive(Clone)]
+pub struct Components<'a> {
+    rest: &'a str,
+    state: ComponentsState
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ComponentsState {
+    Mount,
+    Root,
+    Body
+}
+
+impl<'a> Component<'a> {
+    pub fn as_str(self) -> &'a str {
+        match self {
+            Component::Mount(name) => name,
+            Component::RootDir => "/",
+            Component::CurDir => ".",
+            Component::ParentDir => "..",
+            Component::Normal(name) => name
+        }
+    }
+}
+
+impl<'a> Iterator for Components<'a> {
+    type Item = Component<'a>;
+
+    fn next(&mut self) -> Option<Component<'a>> {
+        loop {
+            match self.state {
+                ComponentsState::Mount => {
+                    self.state = ComponentsState::Root;
+                    if let Some(len) = mount_name_len(self.rest) {
+                        let name = &self.rest[..len + 1];
+                        self.rest = &self.rest[len + 1..];
+                        return Some(Component::Mount(name));
+                    }
+                },
+                ComponentsState::Root => {
+                    self.state = ComponentsState::Body;
+                    if self.rest.starts_with('/') {
+                        self.rest = self.rest.trim_start_matches('/');
+                        return Some(Component::RootDir);
+                    }
+                },
+                ComponentsState::Body => {
+                    if self.rest.is_empty() {
+                        return None;
+                    }
+                    let (segment, rest) = match self.rest.find('/') {
+                        Some(i) => (&self.rest[..i], self.rest[i..].trim_start_matches('/')),
+                        None => (self.rest, "")
+                    };
+                    self.rest = rest;
+                    return Some(match segment {
+                        "." => Component::CurDir,
+                        ".." => Component::ParentDir,
+                        name => Component::Normal(name)
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Length of the mount name if the path starts with one, a mount name is everything before a ':' that comes before any '/'
+fn mount_name_len(path: &str) -> Option<usize> {
+    let colon = path.find(':')?;
+    match path.find('/') {
+        Some(slash) if slash < colon => None,
+        _ => Some(colon)
+    }
+}
+
+impl Path {
+    pub fn new<S: AsRef<str> + ?Sized>(path: &S) -> &Path {
+        unsafe {
+            &*(path.as_ref() as *const str as *const Path)
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf {
+            inner: String::from(&self.inner)
+        }
+    }
+
+    pub fn mount_name(&self) -> Option<&str> {
+        mount_name_len(&self.inner).map(|len| &self.inner[..len])
+    }
+
+    // Everything after the mount name's ':'
+    pub fn without_mount(&self) -> &Path {
+        match mount_name_len(&self.inner) {
+            Some(len) => Path::new(&self.inner[len + 1..]),
+            None => self
+        }
+    }
+
+    pub fn has_root(&self) -> bool {
+        self.without_mount().inner.starts_with('/')
+    }
+
+    // Only absolute paths can be handed to nn::fs
+    pub fn is_absolute(&self) -> bool {
+        self.mount_name().is_some() && self.has_root()
+    }
+
+    pub fn components(&self) -> Components<'_> {
+        Components {
+            rest: &self.inner,
+            state: ComponentsState::Mount
+        }
+    }
+
+    // Length of the mount name, its ':' and any root slashes
+    fn prefix_len(&self) -> usize {
+        let without_mount = &self.without_mount().inner;
+        let mount_len = self.inner.len() - without_mount.len();
+        mount_len + (without_mount.len() - without_mount.trim_start_matches('/').len())
+    }
+
+    pub fn parent(&self) -> Option<&Path> {
+        let prefix_len = self.prefix_len();
+        let body = self.inner[prefix_len..].trim_end_matches('/');
+        if body.is_empty() {
+            return None;
+        }
+        let parent_len = match body.rfind('/') {
+            Some(i) => body[..i].trim_end_matches('/').len(),
+            None => 0
+        };
+        Some(Path::new(&self.inner[..prefix_len + parent_len]))
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        match self.components().last()? {
+            Component::Normal(name) => Some(name),
+            _ => None
+        }
+    }
+
+    pub fn file_stem(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        match name.rfind('.') {
+            Some(0) | None => Some(name),
+            Some(i) => Some(&name[..i])
+        }
+    }
+
+    pub fn extension(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        match name.rfind('.') {
+            Some(0) | None => None,
+            Some(i) => Some(&name[i + 1..])
+        }
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let mut buf = self.to_path_buf();
+        buf.push(path);
+        buf
+    }
+
+    pub fn with_extension<S: AsRef<str>>(&self, extension: S) -> PathBuf {
+        let mut buf = self.to_path_buf();
+        buf.set_extension(extension);
+        buf
+    }
+
+    pub fn starts_with<P: AsRef<Path>>(&self, base: P) -> bool {
+        let mut components = self.components();
+        base.as_ref().components().all(|c| components.next() == Some(c))
+    }
+
+    // What's left after base's components, relative and without leading slashes
+    pub fn strip_prefix<P: AsRef<Path>>(&self, base: P) -> Option<&Path> {
+        let mut components = self.components();
+        if base.as_ref().components().all(|c| components.next() == Some(c)) {
+            Some(Path::new(components.rest.trim_start_matches('/')))
+        } else {
+            None
+        }
+    }
+
+    // Lexically resolves "." and ".." components and collapses repeated slashes.
+    // ".." at the root of a mount stays at the root, the same way nn::fs treats it.
+    pub fn normalize(&self) -> PathBuf {
+        let mut prefix = String::new();
+        let mut parts: alloc::vec::Vec<&str> = alloc::vec::Vec::new();
+        let mut has_root = false;
+        for component in self.components() {
+            match component {
+                Component::Mount(name) => prefix.push_str(name),
+                Component::RootDir => {
+                    has_root = true;
+                    prefix.push('/');
+                },
+                Component::CurDir => {},
+                Component::ParentDir => match parts.last() {
+                    Some(&last) if last != ".." => {
+                        parts.pop();
+                    },
+                    _ if has_root => {},
+                    _ => parts.push("..")
+                },
+                Component::Normal(name) => parts.push(name)
+            }
+        }
+
+        let mut inner = prefix;
+        for (i, part) in parts.iter().enumerate() {
+            if i != 0 {
+                inner.push('/');
+            }
+            inner.push_str(part);
+        }
+        PathBuf {
+            inner
+        }
+    }
+
+    // Checks the mount name and path length against the limits nn::fs enforces. The SDK caps the whole path
+    // after the mount name at the entry name limit, so no single entry name can go over it either.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(name) = self.mount_name() {
+            if name.is_empty() || name.len() > MOUNT_NAME_LENGTH_MAX {
+                return Err(super::result::INVALID_MOUNT_NAME.into());
+            }
+        }
+        if self.without_mount().inner.len() > ENTRY_NAME_BYTE_LENGTH_MAX {
+            return Err(super::result::TOO_LONG_PATH.into());
+        }
+        Ok(())
+    }
+}
+
+impl PathBuf {
+    pub const fn new() -> Self {
+        Self {
+            inner: String::new()
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: String::with_capacity(capacity)
+        }
+    }
+
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.inner)
+    }
+
+    pub fn into_string(self) -> String {
+        self.inner
+    }
+
+    // A path with a mount name replaces this one entirely, a path starting with '/' replaces everything but the mount name
+    pub fn push<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if path.mount_name().is_some() {
+            self.inner.clear();
+        } else if path.has_root() {
+            let mount_len = self.inner.len() - self.without_mount().inner.len();
+            self.inner.truncate(mount_len);
+        } else if !self.inner.is_empty() && !self.inner.ends_with('/') && !self.inner.ends_with(':') {
+            self.inner.push('/');
+        }
+        self.inner.push_str(&path.inner);
+    }
+
+    pub fn pop(&mut self) -> bool {
+        match self.parent().map(|parent| parent.inner.len()) {
+            Some(len) => {
+                self.inner.truncate(len);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn set_file_name<S: AsRef<str>>(&mut self, file_name: S) {
+        if self.file_name().is_some() {
+            self.pop();
+        }
+        self.push(file_name.as_ref());
+    }
+
+    pub fn set_extension<S: AsRef<str>>(&mut self, extension: S) -> bool {
+        let stem_end = match self.file_stem() {
+            Some(stem) => stem.as_ptr() as usize - self.inner.as_ptr() as usize + stem.len(),
+            None => return false
+        };
+        self.inner.truncate(stem_end);
+        let extension = extension.as_ref();
+        if !extension.is_empty() {
+            self.inner.push('.');
+            self.inner.push_str(extension);
+        }
+        true
+    }
+}
+
+impl Deref for PathBuf {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl Borrow<Path> for PathBuf {
+    fn borrow(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl ToOwned for Path {
+    type Owned = PathBuf;
+
+    fn to_owned(&self) -> PathBuf {
+        self.to_path_buf()
+    }
+}
+
+impl From<&str> for PathBuf {
+    fn from(path: &str) -> Self {
+        Self {
+            inner: String::from(path)
+        }
+    }
+}
+
+impl From<String> for PathBuf {
+    fn from(inner: String) -> Self {
+        Self {
+            inner
+        }
+    }
+}
+
+impl From<&Path> for PathBuf {
+    fn from(path: &Path) -> Self {
+        path.to_path_buf()
+    }
+}
+
+// Lets paths go straight into every nn::fs wrapper, which all take AsRef<str>
+impl AsRef<str> for Path {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl AsRef<str> for PathBuf {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl AsRef<Path> for Path {
+    fn as_ref(&self) -> &Path {
+        self
+    }
+}
+
+impl AsRef<Path> for PathBuf {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl AsRef<Path> for str {
+    fn as_ref(&self) -> &Path {
+        Path::new(self)
+    }
+}
+
+impl AsRef<Path> for String {
+    fn as_ref(&self) -> &Path {
+        Path::new(self)
+    }
+}
+
+impl AsRef<Path> for Component<'_> {
+    fn as_ref(&self) -> &Path {
+        Path::new(self.as_str())
+    }
+}
+
+impl fmt::Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Debug for PathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl fmt::Display for PathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use super::{mount_name_len, Component, Path, PathBuf};
+
+    #[test]
+    fn mount_name_must_come_before_any_slash() {
+        assert_eq!(mount_name_len("sd:/a"), Some(2));
+        assert_eq!(mount_name_len("sd:"), Some(2));
+        assert_eq!(mount_name_len("a/b:c"), None);
+        assert_eq!(mount_name_len("/a"), None);
+        assert_eq!(Path::new("a/b:c").mount_name(), None);
+        assert!(!Path::new("a/b:c").is_absolute());
+    }
+
+    #[test]
+    fn components_keep_the_mount_colon() {
+        let components: Vec<_> = Path::new("sd:/dir//file.txt").components().collect();
+        assert_eq!(components, [Component::Mount("sd:"), Component::RootDir, Component::Normal("dir"), Component::Normal("file.txt")]);
+        assert_eq!(Path::new("sd:/a").mount_name(), Some("sd"));
+
+        let mut rebuilt = PathBuf::new();
+        for component in components {
+            rebuilt.push(component);
+        }
+        assert_eq!(rebuilt.as_str(), "sd:/dir/file.txt");
+    }
+
+    #[test]
+    fn parent() {
+        assert_eq!(Path::new("sd:/").parent(), None);
+        assert_eq!(Path::new("sd:").parent(), None);
+        assert_eq!(Path::new("sd:/a").parent(), Some(Path::new("sd:/")));
+        assert_eq!(Path::new("sd:/a/b/").parent(), Some(Path::new("sd:/a")));
+        assert_eq!(Path::new("a").parent(), Some(Path::new("")));
+    }
+
+    #[test]
+    fn strip_prefix() {
+        assert_eq!(Path::new("sd:/a/b/c").strip_prefix("sd:/a"), Some(Path::new("b/c")));
+        assert_eq!(Path::new("sd://a//b").strip_prefix("sd:/a/"), Some(Path::new("b")));
+        assert_eq!(Path::new("sd:/a").strip_prefix("sd:"), Some(Path::new("a")));
+        assert_eq!(Path::new("sd:/a").strip_prefix("sd:/a"), Some(Path::new("")));
+        assert_eq!(Path::new("sd:/ab").strip_prefix("sd:/a"), None);
+        assert_eq!(Path::new("rom:/a").strip_prefix("sd:/"), None);
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(Path::new("sd:/../a").normalize().as_str(), "sd:/a");
+        assert_eq!(Path::new("sd:/a/./b/../c").normalize().as_str(), "sd:/a/c");
+        assert_eq!(Path::new("a/../..").normalize().as_str(), "..");
+        assert_eq!(Path::new("../a/..").normalize().as_str(), "..");
+        assert_eq!(Path::new("sd://a//b/").normalize().as_str(), "sd:/a/b");
+    }
+
+    #[test]
+    fn push() {
+        let mut path = PathBuf::from("sd:/a/b");
+        path.push("c");
+        assert_eq!(path.as_str(), "sd:/a/b/c");
+        path.push("/d");
+        assert_eq!(path.as_str(), "sd:/d");
+        path.push("rom:/e");
+        assert_eq!(path.as_str(), "rom:/e");
+
+        let mut path = PathBuf::from("sd:");
+        path.push("a");
+        assert_eq!(path.as_str(), "sd:a");
+    }
+
+    #[test]
+    fn extensions() {
+        let mut path = PathBuf::from("sd:/.hidden");
+        assert_eq!(path.file_stem(), Some(".hidden"));
+        assert_eq!(path.extension(), None);
+        assert!(path.set_extension("txt"));
+        assert_eq!(path.as_str(), "sd:/.hidden.txt");
+        assert!(path.set_extension("bin"));
+        assert_eq!(path.as_str(), "sd:/.hidden.bin");
+        assert!(path.set_extension(""));
+        assert_eq!(path.as_str(), "sd:/.hidden");
+        assert!(!PathBuf::from("sd:/").set_extension("txt"));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(Path::new("sd:/a").validate().is_ok());
+        assert!(Path::new(":/a").validate().is_err());
+        assert!(Path::new("sixteen_letters_:/a").validate().is_err());
+        let long = alloc::format!("sd:/{}", "a".repeat(super::ENTRY_NAME_BYTE_LENGTH_MAX));
+        assert!(Path::new(&long).validate().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+// Known nn::fs result codes, names follow the SDK's nn::fs::Result* functions
+use super::super::{ErrorModule, Result};
+
+pub const MODULE: u32 = ErrorModule::Fs.as_raw();
+
+pub const PATH_NOT_FOUND: Result = Result::new(MODULE, 1);
+pub const PATH_ALREADY_EXISTS: Result = Result::new(MODULE, 2);
+pub const TARGET_LOCKED: Result = Result::new(MODULE, 7);
+pub const DIRECTORY_NOT_EMPTY: Result = Result::new(MODULE, 8);
+pub const DIRECTORY_STATUS_CHANGED: Result = Result::new(MODULE, 13);
+pub const USABLE_SPACE_NOT_ENOUGH: Result = Result::new(MODULE, 30);
+pub const UNSUPPORTED_SDK_VERSION: Result = Result::new(MODULE, 50);
+pub const MOUNT_NAME_ALREADY_EXISTS: Result = Result::new(MODULE, 60);
+pub const TARGET_NOT_FOUND: Result = Result::new(MODULE, 1002);
+pub const SD_CARD_ACCESS_FAILED: Result = Result::new(MODULE, 2000);
+pub const SD_CARD_NOT_PRESENT: Result = Result::new(MODULE, 2001);
+pub const GAME_CARD_ACCESS_FAILED: Result = Result::new(MODULE, 2500);
+pub const NOT_IMPLEMENTED: Result = Result::new(MODULE, 3001);
+pub const OUT_OF_RANGE: Result = Result::new(MODULE, 3005);
+pub const ALLOCATION_MEMORY_FAILED: Result = Result::new(MODULE, 3200);
+pub const DATA_CORRUPTED: Result = Result::new(MODULE, 4000);
+pub const UNEXPECTED: Result = Result::new(MODULE, 5000);
+pub const PRECONDITION_VIOLATION: Result = Result::new(MODULE, 6000);
+pub const INVALID_ARGUMENT: Result = Result::new(MODULE, 6001);
+pub const INVALID_PATH: Result = Result::new(MODULE, 6002);
+pub const TOO_LONG_PATH: Result = Result::new(MODULE, 6003);
+pub const INVALID_CHARACTER: Result = Result::new(MODULE, 6004);
+pub const INVALID_PATH_FORMAT: Result = Result::new(MODULE, 6005);
+pub const DIRECTORY_UNOBTAINABLE: Result = Result::new(MODULE, 6006);
+pub const NOT_NORMALIZED: Result = Result::new(MODULE, 6007);
+pub const DIRECTORY_NOT_DELETABLE: Result = Result::new(MODULE, 6031);
+pub const DIRECTORY_NOT_RENAMABLE: Result = Result::new(MODULE, 6032);
+pub const INCOMPATIBLE_PATH: Result = Result::new(MODULE, 6033);
+pub const RENAME_TO_OTHER_FILE_SYSTEM: Result = Result::new(MODULE, 6034);
+pub const INVALID_OFFSET: Result = Result::new(MODULE, 6061);
+pub const INVALID_SIZE: Result = Result::new(MODULE, 6062);
+pub const NULLPTR_ARGUMENT: Result = Result::new(MODULE, 6063);
+pub const INVALID_MOUNT_NAME: Result = Result::new(MODULE, 6065);
+pub const INVALID_OPEN_MODE: Result = Result::new(MODULE, 6072);
+pub const FILE_EXTENSION_WITHOUT_OPEN_MODE_ALLOW_APPEND: Result = Result::new(MODULE, 6201);
+pub const READ_NOT_PERMITTED: Result = Result::new(MODULE, 6202);
+pub const WRITE_NOT_PERMITTED: Result = Result::new(MODULE, 6203);
+pub const UNSUPPORTED_OPERATION: Result = Result::new(MODULE, 6300);
+pub const PERMISSION_DENIED: Result = Result::new(MODULE, 6400);
+pub const NEED_FLUSH: Result = Result::new(MODULE, 6454);
+pub const FILE_NOT_CLOSED: Result = Result::new(MODULE, 6455);
+pub const DIRECTORY_NOT_CLOSED: Result = Result::new(MODULE, 6456);
+pub const WRITE_MODE_FILE_NOT_CLOSED: Result = Result::new(MODULE, 6457);
+pub const OPEN_COUNT_LIMIT: Result = Result::new(MODULE, 6709);
+pub const NOT_MOUNTED: Result = Result::new(MODULE, 6905);
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use super::super::super::{ErrorModule, Result};
+
+    #[test]
+    fn known_codes() {
+        assert_eq!(super::PATH_NOT_FOUND.raw(), 0x202);
+        assert_eq!(super::PATH_NOT_FOUND, Result::from_raw(0x202));
+        assert_eq!(super::PATH_ALREADY_EXISTS.raw(), 0x402);
+        assert_eq!(super::TARGET_LOCKED.get(), (2, 7));
+        assert_eq!(super::NOT_MOUNTED.get(), (2, 6905));
+        assert_eq!(super::DATA_CORRUPTED.error_module(), ErrorModule::Fs);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", super::PATH_NOT_FOUND), "2002-0001");
+        assert_eq!(format!("{}", super::RENAME_TO_OTHER_FILE_SYSTEM), "2002-6034");
+        assert_eq!(format!("{}", super::USABLE_SPACE_NOT_ENOUGH), "2002-0030");
+    }
+}
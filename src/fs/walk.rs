@@ -0,0 +1,444 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+use core::cmp::Ordering;
+use super::{DirectoryEntryType, Error, Path, PathBuf};
+
+type SortFn = Box<dyn FnMut(&WalkEntry, &WalkEntry) -> Ordering>;
+type FilterFn = Box<dyn FnMut(&WalkEntry) -> bool>;
+
+// Recursive directory traversal. The walk keeps its state in a heap allocated stack instead of recursing,
+// and every directory is read in full and closed before descending so only one handle is ever open.
+pub struct WalkDir {
+    root: String,
+    min_depth: usize,
+    max_depth: usize,
+    contents_first: bool,
+    sorter: Option<SortFn>,
+    entry_filter: Option<FilterFn>,
+    glob: Option<String>
+}
+
+#[derive(Clone)]
+pub struct WalkEntry {
+    path: PathBuf,
+    name_start: usize,
+    depth: usize,
+    entry_type: DirectoryEntryType,
+    size: u64
+}
+
+pub struct IntoIter {
+    options: WalkDir,
+    started: bool,
+    stack: Vec<Level>,
+    // Directory yielded last in pre-order walks, only read once the caller asks for the next entry
+    pending: Option<WalkEntry>,
+    // Whether the entry yielded last was a directory, in either order
+    yielded_dir: bool
+}
+
+struct Level {
+    entries: vec::IntoIter<WalkEntry>,
+    // Yielded once all entries are exhausted when walking contents first
+    dir: Option<WalkEntry>
+}
+
+#[dev_inline]
+pub fn walk_dir<S: AsRef<str>>(root: S) -> WalkDir {
+    WalkDir::new(root)
+}
+
+impl WalkDir {
+    pub fn new<S: AsRef<str>>(root: S) -> Self {
+        // Trailing separators would end up in the root's name, but a mount root like "sd:/" keeps its own
+        let root = root.as_ref();
+        let trimmed = root.trim_end_matches('/');
+        let root = if trimmed.is_empty() || trimmed.ends_with(':') { root } else { trimmed };
+        Self {
+            root: String::from(root),
+            min_depth: 0,
+            max_depth: usize::MAX,
+            contents_first: false,
+            sorter: None,
+            entry_filter: None,
+            glob: None
+        }
+    }
+
+    // The root itself is depth 0, its children are depth 1 and so on
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    // Yield the contents of a directory before the directory itself (post-order)
+    pub fn contents_first(mut self, contents_first: bool) -> Self {
+        self.contents_first = contents_first;
+        self
+    }
+
+    pub fn sort_by<F: FnMut(&WalkEntry, &WalkEntry) -> Ordering + 'static>(mut self, cmp: F) -> Self {
+        self.sorter = Some(Box::new(cmp));
+        self
+    }
+
+    pub fn sort_by_name(self) -> Self {
+        self.sort_by(|a, b| a.name().cmp(b.name()))
+    }
+
+    // Entries rejected by the predicate are not yielded, and directories rejected by it are not descended into
+    pub fn filter_entry<F: FnMut(&WalkEntry) -> bool + 'static>(mut self, predicate: F) -> Self {
+        self.entry_filter = Some(Box::new(predicate));
+        self
+    }
+
+    // Only yield entries matching a glob pattern. Patterns without a '/' are matched against the entry name,
+    // otherwise against the path relative to the root. '*' and '?' never match a '/', '**' matches anything.
+    pub fn glob<S: AsRef<str>>(mut self, pattern: S) -> Self {
+        self.glob = Some(String::from(pattern.as_ref()));
+        self
+    }
+}
+
+impl IntoIterator for WalkDir {
+    type Item = Result<WalkEntry, Error>;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            started: false,
+            stack: Vec::new(),
+            pending: None,
+            yielded_dir: false,
+            options: self
+        }
+    }
+}
+
+impl WalkEntry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn name(&self) -> &str {
+        &self.path.as_str()[self.name_start..]
+    }
+
+    pub const fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub const fn entry_type(&self) -> DirectoryEntryType {
+        self.entry_type
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == DirectoryEntryType::Directory
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.entry_type == DirectoryEntryType::File
+    }
+
+    // Always 0 for directories
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl IntoIter {
+    // Don't descend into the directory that was yielded last, or if that was a file, skip the rest of its parent.
+    // When walking contents first a yielded directory has already been walked, so skipping it does nothing.
+    pub fn skip_current_dir(&mut self) {
+        if self.yielded_dir {
+            self.pending = None;
+            self.yielded_dir = false;
+        } else if let Some(level) = self.stack.pop() {
+            if let Some(dir) = level.dir {
+                self.stack.push(Level {
+                    entries: Vec::new().into_iter(),
+                    dir: Some(dir)
+                });
+            }
+        }
+    }
+
+    fn root_entry(&self) -> Result<WalkEntry, Error> {
+        let root = &self.options.root;
+        let entry_type = super::get_entry_type(root)?;
+        let size = match entry_type {
+            DirectoryEntryType::File => {
+                let file = super::File::open(root, super::OpenMode::READ)?;
+                file.len()?
+            },
+            DirectoryEntryType::Directory => 0
+        };
+        let name_start = root.trim_end_matches('/').rfind('/').map(|i| i + 1).unwrap_or(0);
+        Ok(WalkEntry {
+            path: PathBuf::from(root.as_str()),
+            name_start,
+            depth: 0,
+            entry_type,
+            size
+        })
+    }
+
+    fn read_children(&mut self, dir: &WalkEntry) -> Result<Vec<WalkEntry>, Error> {
+        let mut children = Vec::new();
+        for entry in super::read_dir(&dir.path)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name_start = path.as_str().len() - entry.name().len();
+            children.push(WalkEntry {
+                path,
+                name_start,
+                depth: dir.depth + 1,
+                entry_type: entry.entry_type(),
+                size: entry.size()
+            });
+        }
+        if let Some(sorter) = self.options.sorter.as_mut() {
+            children.sort_by(|a, b| sorter(a, b));
+        }
+        Ok(children)
+    }
+
+    fn push_dir(&mut self, dir: WalkEntry) -> Option<Error> {
+        let (children, error) = match self.read_children(&dir) {
+            Ok(children) => (children, None),
+            Err(e) => (Vec::new(), Some(e))
+        };
+        self.stack.push(Level {
+            entries: children.into_iter(),
+            dir: if self.options.contents_first { Some(dir) } else { None }
+        });
+        error
+    }
+
+    fn is_yielded(&self, entry: &WalkEntry) -> bool {
+        if entry.depth < self.options.min_depth {
+            return false;
+        }
+        match self.options.glob.as_ref() {
+            Some(pattern) if pattern.contains('/') => {
+                let relative = entry.path.as_str()[self.options.root.len()..].trim_start_matches('/');
+                glob_match(pattern, relative)
+            },
+            Some(pattern) => glob_match(pattern, entry.name()),
+            None => true
+        }
+    }
+
+    // Returns the entry if it should be yielded right away
+    fn visit(&mut self, entry: WalkEntry) -> Option<Result<WalkEntry, Error>> {
+        if let Some(filter) = self.options.entry_filter.as_mut() {
+            if !filter(&entry) {
+                return None;
+            }
+        }
+
+        let descend = entry.is_dir() && entry.depth < self.options.max_depth;
+        if descend && self.options.contents_first {
+            return self.push_dir(entry).map(Err);
+        }
+
+        if !self.is_yielded(&entry) {
+            if descend {
+                return self.push_dir(entry).map(Err);
+            }
+            return None;
+        }
+
+        if descend {
+            self.pending = Some(entry.clone());
+        }
+        Some(Ok(entry))
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = Result<WalkEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_entry();
+        self.yielded_dir = matches!(&item, Some(Ok(entry)) if entry.is_dir());
+        item
+    }
+}
+
+impl IntoIter {
+    fn next_entry(&mut self) -> Option<Result<WalkEntry, Error>> {
+        if !self.started {
+            self.started = true;
+            match self.root_entry() {
+                Ok(root) => {
+                    if let Some(item) = self.visit(root) {
+                        return Some(item);
+                    }
+                },
+                Err(e) => return Some(Err(e))
+            }
+        }
+
+        loop {
+            if let Some(dir) = self.pending.take() {
+                if let Some(e) = self.push_dir(dir) {
+                    return Some(Err(e));
+                }
+            }
+
+            let level = self.stack.last_mut()?;
+            match level.entries.next() {
+                Some(entry) => {
+                    if let Some(item) = self.visit(entry) {
+                        return Some(item);
+                    }
+                },
+                None => {
+                    if let Some(dir) = self.stack.pop().unwrap().dir {
+                        if self.is_yielded(&dir) {
+                            return Some(Ok(dir));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+enum GlobToken {
+    Literal(u8),
+    AnyChar,
+    AnyInSegment,
+    AnyDirs,
+    Any
+}
+
+fn tokenize_glob(pattern: &str) -> Vec<GlobToken> {
+    let pattern = pattern.as_bytes();
+    let mut tokens = Vec::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < pattern.len() {
+        let token = match pattern[i] {
+            b'*' if pattern.get(i + 1) == Some(&b'*') => {
+                i += 1;
+                if pattern.get(i + 1) == Some(&b'/') {
+                    i += 1;
+                    GlobToken::AnyDirs
+                } else {
+                    GlobToken::Any
+                }
+            },
+            b'*' => GlobToken::AnyInSegment,
+            b'?' => GlobToken::AnyChar,
+            c => GlobToken::Literal(c)
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    tokens
+}
+
+// Iterative so long patterns or paths can't overflow the stack, matched[j] tracks whether text[..j] is
+// matched by the tokens processed so far
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let text = text.as_bytes();
+    let mut matched = alloc::vec![false; text.len() + 1];
+    let mut next = alloc::vec![false; text.len() + 1];
+    matched[0] = true;
+
+    for token in tokenize_glob(pattern) {
+        next.iter_mut().for_each(|x| *x = false);
+        match token {
+            GlobToken::Literal(c) => {
+                for j in 0..text.len() {
+                    next[j + 1] = matched[j] && text[j] == c;
+                }
+            },
+            GlobToken::AnyChar => {
+                for j in 0..text.len() {
+                    next[j + 1] = matched[j] && text[j] != b'/';
+                }
+            },
+            GlobToken::AnyInSegment | GlobToken::Any => {
+                let crosses_dirs = matches!(token, GlobToken::Any);
+                let mut reachable = false;
+                for j in 0..=text.len() {
+                    reachable |= matched[j];
+                    next[j] = reachable;
+                    if j < text.len() && text[j] == b'/' && !crosses_dirs {
+                        reachable = false;
+                    }
+                }
+            },
+            // Zero or more whole directories, including their trailing '/'
+            GlobToken::AnyDirs => {
+                let mut reachable = false;
+                for j in 0..=text.len() {
+                    reachable |= matched[j];
+                    next[j] = matched[j] || (reachable && j > 0 && text[j - 1] == b'/');
+                }
+            }
+        }
+        core::mem::swap(&mut matched, &mut next);
+    }
+
+    matched[text.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn literals_and_question_marks() {
+        assert!(glob_match("a.txt", "a.txt"));
+        assert!(!glob_match("a.txt", "a.txt2"));
+        assert!(glob_match("?.txt", "a.txt"));
+        assert!(!glob_match("?.txt", "ab.txt"));
+        assert!(!glob_match("a?b", "a/b"));
+    }
+
+    #[test]
+    fn star_stays_in_its_segment() {
+        assert!(glob_match("*.txt", "a.txt"));
+        assert!(glob_match("*.txt", ".txt"));
+        assert!(!glob_match("*.txt", "dir/a.txt"));
+        assert!(glob_match("dir/*", "dir/a.txt"));
+        assert!(!glob_match("dir/*", "dir/sub/a.txt"));
+        assert!(glob_match("*/*.txt", "dir/a.txt"));
+    }
+
+    #[test]
+    fn double_star_slash_matches_zero_or_more_dirs() {
+        assert!(glob_match("**/a.txt", "a.txt"));
+        assert!(glob_match("**/a.txt", "dir/a.txt"));
+        assert!(glob_match("**/a.txt", "dir/sub/a.txt"));
+        assert!(!glob_match("**/a.txt", "dir/ba.txt"));
+        assert!(glob_match("dir/**/*.txt", "dir/a.txt"));
+        assert!(glob_match("dir/**/*.txt", "dir/sub/deeper/a.txt"));
+        assert!(!glob_match("dir/**/*.txt", "other/a.txt"));
+    }
+
+    #[test]
+    fn double_star_crosses_dirs() {
+        assert!(glob_match("dir/**", "dir/sub/a.txt"));
+        assert!(glob_match("**.txt", "dir/sub/a.txt"));
+        assert!(glob_match("**", ""));
+    }
+
+    #[test]
+    fn empty_text_and_pattern() {
+        assert!(glob_match("", ""));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("", "a"));
+        assert!(!glob_match("a", ""));
+    }
+}
@@ -0,0 +1,386 @@
+// core-only stand-in for the parts of std::io that are useful on top of nn::fs
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+pub use super::fs::{Error, ErrorKind};
+
+mod buffered;
+pub use buffered::{BufReader, BufWriter};
+
+const DEFAULT_BUF_SIZE: usize = 0x2000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64)
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+pub trait Read {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::new(ErrorKind::UnexpectedEof)),
+                n => buf = &mut buf[n..]
+            }
+        }
+        Ok(())
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        loop {
+            let filled = buf.len();
+            buf.resize(filled + DEFAULT_BUF_SIZE, 0);
+            match self.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    buf.truncate(filled);
+                    return Ok(filled - start);
+                },
+                Ok(n) => buf.truncate(filled + n),
+                Err(e) => {
+                    buf.truncate(filled);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let read = self.read_to_end(&mut bytes)?;
+        match core::str::from_utf8(&bytes) {
+            Ok(s) => {
+                buf.push_str(s);
+                Ok(read)
+            },
+            Err(_) => Err(Error::new(ErrorKind::InvalidData))
+        }
+    }
+
+    fn by_ref(&mut self) -> &mut Self where Self: Sized {
+        self
+    }
+}
+
+pub trait Write {
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    fn flush(&mut self) -> Result<()>;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::new(ErrorKind::WriteZero)),
+                n => buf = &buf[n..]
+            }
+        }
+        Ok(())
+    }
+
+    // Writes all of buf and flushes in a single step that either succeeds or writes nothing, like a WriteFile
+    // with WriteOptions::FLUSH. Writers that can't guarantee that return None.
+    fn write_all_and_flush(&mut self, _buf: &[u8]) -> Option<Result<()>> {
+        None
+    }
+
+    fn by_ref(&mut self) -> &mut Self where Self: Sized {
+        self
+    }
+}
+
+pub trait Seek {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    fn rewind(&mut self) -> Result<()> {
+        self.seek(SeekFrom::Start(0)).map(|_| ())
+    }
+
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+pub trait BufRead: Read {
+    fn fill_buf(&mut self) -> Result<&[u8]>;
+
+    fn consume(&mut self, amt: usize);
+
+    fn read_until(&mut self, delim: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut read = 0;
+        loop {
+            let (done, used) = {
+                let available = self.fill_buf()?;
+                match available.iter().position(|&b| b == delim) {
+                    Some(i) => {
+                        buf.extend_from_slice(&available[..=i]);
+                        (true, i + 1)
+                    },
+                    None => {
+                        buf.extend_from_slice(available);
+                        (available.is_empty(), available.len())
+                    }
+                }
+            };
+            self.consume(used);
+            read += used;
+            if done {
+                return Ok(read);
+            }
+        }
+    }
+
+    fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let read = self.read_until(b'\n', &mut bytes)?;
+        match core::str::from_utf8(&bytes) {
+            Ok(s) => {
+                buf.push_str(s);
+                Ok(read)
+            },
+            Err(_) => Err(Error::new(ErrorKind::InvalidData))
+        }
+    }
+}
+
+pub fn copy<R: Read + ?Sized, W: Write + ?Sized>(reader: &mut R, writer: &mut W) -> Result<u64> {
+    // Kept on the heap, plugin threads tend to run with very small stacks
+    let mut buf = alloc::vec![0u8; DEFAULT_BUF_SIZE];
+    let mut written = 0;
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            return Ok(written);
+        }
+        writer.write_all(&buf[..read])?;
+        written += read as u64;
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Cursor<T> {
+    inner: T,
+    position: u64
+}
+
+impl<T> Cursor<T> {
+    pub const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            position: 0
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    pub const fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub const fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+    }
+}
+
+impl<T: AsRef<[u8]>> Cursor<T> {
+    fn remaining_slice(&self) -> &[u8] {
+        let inner = self.inner.as_ref();
+        let start = core::cmp::min(self.position, inner.len() as u64) as usize;
+        &inner[start..]
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for Cursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = Read::read(&mut self.remaining_slice(), buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<T: AsRef<[u8]>> BufRead for Cursor<T> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(self.remaining_slice())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt as u64;
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for Cursor<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            },
+            SeekFrom::End(offset) => (self.inner.as_ref().len() as u64, offset),
+            SeekFrom::Current(offset) => (self.position, offset)
+        };
+
+        let new_position = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+
+        match new_position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            },
+            None => Err(Error::new(ErrorKind::InvalidInput))
+        }
+    }
+}
+
+impl Write for Cursor<&mut [u8]> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let start = core::cmp::min(self.position, self.inner.len() as u64) as usize;
+        let written = Write::write(&mut &mut self.inner[start..], buf)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Cursor<Vec<u8>> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        // A position past what the Vec can address can't be written at
+        let start = usize::try_from(self.position).map_err(|_| Error::new(ErrorKind::InvalidInput))?;
+        let end = start.checked_add(buf.len()).ok_or(Error::new(ErrorKind::InvalidInput))?;
+        if self.inner.len() < end {
+            self.inner.resize(end, 0);
+        }
+        self.inner[start..end].copy_from_slice(buf);
+        self.position = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let amt = core::cmp::min(buf.len(), self.len());
+        let (a, b) = self.split_at(amt);
+        buf[..amt].copy_from_slice(a);
+        *self = b;
+        Ok(amt)
+    }
+}
+
+impl BufRead for &[u8] {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(*self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        *self = &self[amt..];
+    }
+}
+
+impl Write for &mut [u8] {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let amt = core::cmp::min(buf.len(), self.len());
+        let (a, b) = core::mem::take(self).split_at_mut(amt);
+        a.copy_from_slice(&buf[..amt]);
+        *self = b;
+        Ok(amt)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for &mut B {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn write_all_and_flush(&mut self, buf: &[u8]) -> Option<Result<()>> {
+        (**self).write_all_and_flush(buf)
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (**self).seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use super::{Cursor, ErrorKind, Write};
+
+    #[test]
+    fn vec_cursor_writes_past_the_end() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.set_position(2);
+        cursor.write_all(b"ab").unwrap();
+        assert_eq!(cursor.get_ref(), b"\0\0ab");
+        assert_eq!(cursor.position(), 4);
+    }
+
+    #[test]
+    fn vec_cursor_rejects_unaddressable_positions() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.set_position(u64::MAX);
+        assert_eq!(cursor.write(b"a").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(cursor.get_ref().is_empty());
+    }
+}
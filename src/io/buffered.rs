@@ -0,0 +1,274 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use super::{BufRead, Read, Result, Seek, SeekFrom, Write, DEFAULT_BUF_SIZE};
+
+// Every read on nn::fs is an IPC round trip, so small reads are served from an internal buffer instead
+pub struct BufReader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize
+}
+
+impl<R: Read> BufReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: alloc::vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0
+        }
+    }
+}
+
+impl<R> BufReader<R> {
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    // Any data still sitting in the buffer is lost
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Nothing buffered and the request is at least as big as our buffer, skip the copy
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            self.discard_buffer();
+            return self.inner.read(buf);
+        }
+        let read = {
+            let mut available = self.fill_buf()?;
+            available.read(buf)?
+        };
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos >= self.filled {
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = core::cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl<R: Seek> Seek for BufReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let result = match pos {
+            // The inner cursor is ahead of ours by whatever is still buffered
+            SeekFrom::Current(offset) => {
+                let remaining = (self.filled - self.pos) as i64;
+                match offset.checked_sub(remaining) {
+                    Some(offset) => self.inner.seek(SeekFrom::Current(offset))?,
+                    None => {
+                        // Too far back to combine, rewind over the buffered data first
+                        self.inner.seek(SeekFrom::Current(-remaining))?;
+                        self.discard_buffer();
+                        self.inner.seek(SeekFrom::Current(offset))?
+                    }
+                }
+            },
+            _ => self.inner.seek(pos)?
+        };
+        self.discard_buffer();
+        Ok(result)
+    }
+}
+
+// Collects small writes and hands them to the inner writer in large chunks.
+// The buffer is flushed when dropped, but errors are lost at that point so call flush yourself.
+pub struct BufWriter<W: Write> {
+    inner: Option<W>,
+    buf: Vec<u8>
+}
+
+impl<W: Write> BufWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            buf: Vec::with_capacity(capacity)
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush_buf()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn flush_buf(&mut self) -> Result<()> {
+        let inner = self.inner.as_mut().unwrap();
+        let mut written = 0;
+        let result = loop {
+            if written >= self.buf.len() {
+                break Ok(());
+            }
+            match inner.write(&self.buf[written..]) {
+                Ok(0) => break Err(super::Error::new(super::ErrorKind::WriteZero)),
+                Ok(n) => written += n,
+                Err(e) => break Err(e)
+            }
+        };
+        self.buf.drain(..written);
+        result
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.buf.len() + buf.len() > self.buf.capacity() {
+            self.flush_buf()?;
+        }
+        if buf.len() >= self.buf.capacity() {
+            self.get_mut().write(buf)
+        } else {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.buf.is_empty() {
+            // Lets files commit the tail of the buffer in the same call that writes it. Anything else could
+            // fail halfway, so it's drained as it goes and nothing gets written twice by the next flush.
+            let inner = self.inner.as_mut().unwrap();
+            match inner.write_all_and_flush(&self.buf) {
+                Some(result) => {
+                    result?;
+                    self.buf.clear();
+                    return Ok(());
+                },
+                None => self.flush_buf()?
+            }
+        }
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write + Seek> Seek for BufWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.flush_buf()?;
+        self.get_mut().seek(pos)
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use super::super::{Cursor, Error, ErrorKind};
+    use super::*;
+
+    // Takes at most `limit` bytes in total, then fails every write
+    struct Limited {
+        written: Vec<u8>,
+        limit: usize,
+        flushes: usize
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let len = core::cmp::min(buf.len(), self.limit - self.written.len());
+            if len == 0 {
+                return Err(Error::new(ErrorKind::WriteZero));
+            }
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_flush_keeps_only_unwritten_bytes() {
+        let mut writer = BufWriter::with_capacity(16, Limited { written: Vec::new(), limit: 3, flushes: 0 });
+        writer.write_all(b"abcdef").unwrap();
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.buffer(), b"def");
+        writer.get_mut().limit = 6;
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().written, b"abcdef");
+        assert_eq!(writer.get_ref().flushes, 1);
+    }
+
+    #[test]
+    fn seek_accounts_for_buffered_data() {
+        let data: Vec<u8> = (0..32).collect();
+        let mut reader = BufReader::with_capacity(8, Cursor::new(&data[..]));
+        let mut byte = [0];
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(reader.seek(SeekFrom::Current(2)).unwrap(), 3);
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(byte, [3]);
+    }
+
+    #[test]
+    fn seek_back_by_i64_min() {
+        let data = [0u8; 32];
+        let mut reader = BufReader::with_capacity(8, Cursor::new(&data[..]));
+        let mut byte = [0];
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(reader.seek(SeekFrom::Current(i64::MIN)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(reader.buffer().is_empty());
+    }
+}
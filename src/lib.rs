@@ -2,15 +2,24 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 #![feature(const_fn_floating_point_arithmetic)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 extern crate alloc;
 
+// The host-side mocks are built on top of std
+#[cfg(any(feature = "mock_fs", feature = "mock_os", feature = "mock_mem"))]
+extern crate std;
+
 #[macro_use]
 extern crate bitflags;
 
 pub mod mem;
 pub mod fs;
+pub mod io;
 pub mod os;
+mod result;
+pub use result::{ErrorModule, Result};
+mod spin;
 pub mod timespan;
 use alloc::{borrow::ToOwned, string::String};
 pub use timespan::TimeSpan;
@@ -21,29 +30,6 @@ extern crate nn_macro;
 
 pub use nn_macro::*;
 
-#[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
-pub struct Result(u32);
-
-impl Result {
-    pub fn new(module: u32, description: u32) -> Self {
-        let description = (description & 0b0001_1111_1111_1111) << 9;
-        let module = module & 0b0001_1111_1111;
-        Self(description | module)
-    }
-
-    pub fn get(self) -> (u32, u32) {
-        let inner = self.0;
-        let module = inner & 0b0001_1111_1111;
-        let description = (module & (0b0001_1111_1111_1111 << 9)) >> 9;
-        (module, description)
-    }
-
-    pub fn is_success(&self) -> bool {
-        self.0 == 0
-    }
-}
-
 #[macro_export]
 macro_rules! get_rust_result {
     ($nx:ident, $ok:expr) => {
@@ -1,5 +1,21 @@
+use core::cell::UnsafeCell;
 use libc::*;
 
+mod arena;
+mod debug;
+mod global;
+mod report;
+pub use arena::{AllocError, ArenaAllocator, ArenaBox, ArenaVec};
+pub use debug::{AllocationInfo, DebugAllocator, DebugError};
+pub use global::NnGlobalAllocator;
+pub use report::{HashDiff, HeapReport};
+
+#[cfg(feature = "mock_mem")]
+mod mock;
+#[cfg(feature = "mock_mem")]
+use mock as standard_allocator_impl;
+
+#[cfg(not(feature = "mock_mem"))]
 mod standard_allocator_impl {
     use libc::*;
     use super::{AllocatorHash, AllocatorWalkCallback, StandardAllocator};
@@ -119,21 +135,31 @@ mod standard_allocator_impl {
 pub type AllocatorWalkCallback = extern "C" fn(address: *mut c_void, size: usize, user_data: *mut c_void) -> bool;
 
 #[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct AllocatorHash {
     pub regions: usize,
     pub total_size: usize,
     pub hash: usize
 }
 
-#[repr(C)]
+// The SDK's object holds pointers, so it needs their alignment even though its contents are opaque here
+#[repr(C, align(8))]
 pub struct StandardAllocator {
-    _x0: [u8; 0x38]
+    _x0: UnsafeCell<[u8; 0x38]>
 }
 
+// StandardAllocator locks internally, so it can be shared between threads and allocated from through a shared reference
+unsafe impl Send for StandardAllocator {}
+unsafe impl Sync for StandardAllocator {}
+
 impl StandardAllocator {
+    fn as_mut_ptr(&self) -> *mut Self {
+        self as *const Self as *mut Self
+    }
+
     pub const fn default() -> Self {
         Self {
-            _x0: [0; 0x38]
+            _x0: UnsafeCell::new([0; 0x38])
         }
     }
 
@@ -179,30 +205,30 @@ impl StandardAllocator {
     }
 
     #[dev_inline]
-    pub fn alloc(&mut self, size: usize) -> *mut c_void {
+    pub fn alloc(&self, size: usize) -> *mut c_void {
         unsafe {
-            standard_allocator_impl::Allocate(self, size)
+            standard_allocator_impl::Allocate(self.as_mut_ptr(), size)
         }
     }
 
     #[dev_inline]
-    pub fn alloc_aligned(&mut self, size: usize, alignment: usize) -> *mut c_void {
+    pub fn alloc_aligned(&self, size: usize, alignment: usize) -> *mut c_void {
         unsafe {
-            standard_allocator_impl::AlignedAllocate(self, size, alignment)
+            standard_allocator_impl::AlignedAllocate(self.as_mut_ptr(), size, alignment)
         }
     }
 
     #[dev_inline]
-    pub fn free(&mut self, address: *mut c_void) {
+    pub fn free(&self, address: *mut c_void) {
         unsafe {
-            standard_allocator_impl::Free(self, address)
+            standard_allocator_impl::Free(self.as_mut_ptr(), address)
         }
     }
 
     #[dev_inline]
-    pub fn realloc(&mut self, address: *mut c_void, new_size: usize) -> *mut c_void {
+    pub fn realloc(&self, address: *mut c_void, new_size: usize) -> *mut c_void {
         unsafe {
-            standard_allocator_impl::Reallocate(self, address, new_size)
+            standard_allocator_impl::Reallocate(self.as_mut_ptr(), address, new_size)
         }
     }
 
@@ -262,6 +288,18 @@ impl StandardAllocator {
             standard_allocator_impl::WalkAllocatedBlocks(self, callback, user_data)
         }
     }
+
+    // Calls f with the address and size of every allocated block until it returns false.
+    // The allocator may be locked while walking, so f must not allocate from it.
+    pub fn for_each_allocated_block<F: FnMut(*mut c_void, usize) -> bool>(&self, mut f: F) {
+        extern "C" fn trampoline<F: FnMut(*mut c_void, usize) -> bool>(address: *mut c_void, size: usize, user_data: *mut c_void) -> bool {
+            unsafe {
+                (*(user_data as *mut F))(address, size)
+            }
+        }
+
+        self.walk_allocated_blocks(trampoline::<F>, &mut f as *mut F as *mut c_void)
+    }
 }
 
 impl Drop for StandardAllocator {
@@ -0,0 +1,444 @@
+use alloc::alloc::handle_alloc_error;
+use core::alloc::Layout;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use libc::c_void;
+use super::StandardAllocator;
+
+// Alignment StandardAllocator::alloc and realloc already guarantee
+const DEFAULT_ALIGNMENT: usize = 0x10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+// Handle for allocating from a specific StandardAllocator, so a collection's memory stays inside that arena.
+// With the allocator_api feature this implements core::alloc::Allocator and works with Box::new_in, Vec::new_in, etc.
+// ArenaBox and ArenaVec cover the common cases without it.
+#[derive(Copy, Clone)]
+pub struct ArenaAllocator<'a> {
+    allocator: &'a StandardAllocator
+}
+
+impl<'a> ArenaAllocator<'a> {
+    pub const fn new(allocator: &'a StandardAllocator) -> Self {
+        Self {
+            allocator
+        }
+    }
+
+    pub const fn allocator(&self) -> &'a StandardAllocator {
+        self.allocator
+    }
+
+    // Zero-sized layouts get a dangling pointer and never reach the StandardAllocator
+    pub fn allocate_raw(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+        let ptr = if layout.align() <= DEFAULT_ALIGNMENT {
+            self.allocator.alloc(layout.size())
+        } else {
+            self.allocator.alloc_aligned(layout.size(), layout.align())
+        };
+        NonNull::new(ptr as *mut u8).ok_or(AllocError)
+    }
+
+    /// # Safety
+    /// `ptr` must have been allocated from this arena with `layout`.
+    pub unsafe fn deallocate_raw(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.allocator.free(ptr.as_ptr() as *mut c_void);
+        }
+    }
+
+    // Resizes a block allocated with old_layout to new_size bytes of the same alignment.
+    // On failure the original block is left untouched.
+    /// # Safety
+    /// `ptr` must have been allocated from this arena with `old_layout`.
+    pub unsafe fn reallocate_raw(&self, ptr: NonNull<u8>, old_layout: Layout, new_size: usize) -> Result<NonNull<u8>, AllocError> {
+        let new_layout = Layout::from_size_align(new_size, old_layout.align()).map_err(|_| AllocError)?;
+        if old_layout.size() == 0 {
+            return self.allocate_raw(new_layout);
+        }
+        if new_size == 0 {
+            self.deallocate_raw(ptr, old_layout);
+            return Ok(dangling(new_layout));
+        }
+        if old_layout.align() <= DEFAULT_ALIGNMENT {
+            let new = self.allocator.realloc(ptr.as_ptr() as *mut c_void, new_size);
+            return NonNull::new(new as *mut u8).ok_or(AllocError);
+        }
+
+        // realloc only keeps the default alignment, so over-aligned blocks are moved by hand
+        let new = self.allocate_raw(new_layout)?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new.as_ptr(), core::cmp::min(old_layout.size(), new_size));
+        self.deallocate_raw(ptr, old_layout);
+        Ok(new)
+    }
+}
+
+fn dangling(layout: Layout) -> NonNull<u8> {
+    unsafe {
+        NonNull::new_unchecked(layout.align() as *mut u8)
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+unsafe impl core::alloc::Allocator for ArenaAllocator<'_> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let ptr = self.allocate_raw(layout).map_err(|_| core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.deallocate_raw(ptr, layout)
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old_layout: Layout, new_layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        self.resize(ptr, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(&self, ptr: NonNull<u8>, old_layout: Layout, new_layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        self.resize(ptr, old_layout, new_layout)
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl ArenaAllocator<'_> {
+    unsafe fn resize(&self, ptr: NonNull<u8>, old_layout: Layout, new_layout: Layout) -> Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let new = if new_layout.align() == old_layout.align() {
+            self.reallocate_raw(ptr, old_layout, new_layout.size())
+        } else {
+            self.allocate_raw(new_layout).inspect(|new| {
+                ptr::copy_nonoverlapping(ptr.as_ptr(), new.as_ptr(), core::cmp::min(old_layout.size(), new_layout.size()));
+                self.deallocate_raw(ptr, old_layout);
+            })
+        };
+        let new = new.map_err(|_| core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(new, new_layout.size()))
+    }
+}
+
+// Owned value stored in an arena, the stable counterpart of Box<T, ArenaAllocator>
+pub struct ArenaBox<'a, T> {
+    ptr: NonNull<T>,
+    arena: ArenaAllocator<'a>,
+    _marker: PhantomData<T>
+}
+
+impl<'a, T> ArenaBox<'a, T> {
+    pub fn new_in(value: T, arena: ArenaAllocator<'a>) -> Self {
+        match Self::try_new_in(value, arena) {
+            Ok(boxed) => boxed,
+            Err(_) => handle_alloc_error(Layout::new::<T>())
+        }
+    }
+
+    // Hands the value back if the arena is full
+    pub fn try_new_in(value: T, arena: ArenaAllocator<'a>) -> Result<Self, T> {
+        match arena.allocate_raw(Layout::new::<T>()) {
+            Ok(ptr) => {
+                let ptr = ptr.cast::<T>();
+                unsafe {
+                    ptr.as_ptr().write(value);
+                }
+                Ok(Self {
+                    ptr,
+                    arena,
+                    _marker: PhantomData
+                })
+            },
+            Err(_) => Err(value)
+        }
+    }
+
+    pub fn arena(this: &Self) -> ArenaAllocator<'a> {
+        this.arena
+    }
+
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            let value = this.ptr.as_ptr().read();
+            this.arena.deallocate_raw(this.ptr.cast(), Layout::new::<T>());
+            value
+        }
+    }
+
+    pub fn leak(this: Self) -> &'a mut T {
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            &mut *this.ptr.as_ptr()
+        }
+    }
+}
+
+impl<T> Deref for ArenaBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe {
+            self.ptr.as_ref()
+        }
+    }
+}
+
+impl<T> DerefMut for ArenaBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {
+            self.ptr.as_mut()
+        }
+    }
+}
+
+impl<T> Drop for ArenaBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            self.arena.deallocate_raw(self.ptr.cast(), Layout::new::<T>());
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for ArenaBox<'_, T> {}
+unsafe impl<T: Sync> Sync for ArenaBox<'_, T> {}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// Growable array stored in an arena, the stable counterpart of Vec<T, ArenaAllocator>
+pub struct ArenaVec<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    arena: ArenaAllocator<'a>,
+    _marker: PhantomData<T>
+}
+
+impl<'a, T> ArenaVec<'a, T> {
+    pub const fn new_in(arena: ArenaAllocator<'a>) -> Self {
+        Self {
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: if mem::size_of::<T>() == 0 { usize::MAX } else { 0 },
+            arena,
+            _marker: PhantomData
+        }
+    }
+
+    pub fn with_capacity_in(capacity: usize, arena: ArenaAllocator<'a>) -> Self {
+        let mut vec = Self::new_in(arena);
+        vec.reserve(capacity);
+        vec
+    }
+
+    pub fn arena(&self) -> ArenaAllocator<'a> {
+        self.arena
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe {
+            core::slice::from_raw_parts(self.ptr.as_ptr(), self.len)
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)
+        }
+    }
+
+    fn layout(capacity: usize) -> Result<Layout, AllocError> {
+        Layout::array::<T>(capacity).map_err(|_| AllocError)
+    }
+
+    // Makes room for at least additional more elements, growing geometrically
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        let required = self.len.checked_add(additional).ok_or(AllocError)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        let capacity = core::cmp::max(core::cmp::max(self.capacity * 2, required), 4);
+        let new_layout = Self::layout(capacity)?;
+        let ptr = unsafe {
+            self.arena.reallocate_raw(self.ptr.cast(), Self::layout(self.capacity)?, new_layout.size())?
+        };
+        self.ptr = ptr.cast();
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        if self.try_reserve(additional).is_err() {
+            let capacity = self.len.saturating_add(additional);
+            handle_alloc_error(Self::layout(capacity).unwrap_or(Layout::new::<T>()));
+        }
+    }
+
+    // Hands the value back if the arena is full
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.try_reserve(1).is_err() {
+            return Err(value);
+        }
+        unsafe {
+            self.ptr.as_ptr().add(self.len).write(value);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.reserve(1);
+        unsafe {
+            self.ptr.as_ptr().add(self.len).write(value);
+        }
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe {
+            Some(self.ptr.as_ptr().add(self.len).read())
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len, "insertion index (is {}) should be <= len (is {})", index, self.len);
+        self.reserve(1);
+        unsafe {
+            let slot = self.ptr.as_ptr().add(index);
+            ptr::copy(slot, slot.add(1), self.len - index);
+            slot.write(value);
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index (is {}) should be < len (is {})", index, self.len);
+        unsafe {
+            let slot = self.ptr.as_ptr().add(index);
+            let value = slot.read();
+            ptr::copy(slot.add(1), slot, self.len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "swap_remove index (is {}) should be < len (is {})", index, self.len);
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop().unwrap()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail = ptr::slice_from_raw_parts_mut(unsafe { self.ptr.as_ptr().add(len) }, self.len - len);
+        // Shrink first so a panicking destructor can't lead to a double drop
+        self.len = len;
+        unsafe {
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    // Returns spare capacity to the arena
+    pub fn shrink_to_fit(&mut self) {
+        if mem::size_of::<T>() == 0 || self.capacity == self.len {
+            return;
+        }
+        let (old_layout, new_layout) = match (Self::layout(self.capacity), Self::layout(self.len)) {
+            (Ok(old_layout), Ok(new_layout)) => (old_layout, new_layout),
+            _ => return
+        };
+        if let Ok(ptr) = unsafe { self.arena.reallocate_raw(self.ptr.cast(), old_layout, new_layout.size()) } {
+            self.ptr = ptr.cast();
+            self.capacity = self.len;
+        }
+    }
+}
+
+impl<T: Clone> ArenaVec<'_, T> {
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.reserve(other.len());
+        for value in other {
+            self.push(value.clone());
+        }
+    }
+}
+
+impl<T> Deref for ArenaVec<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for ArenaVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> Extend<T> for ArenaVec<'_, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> Drop for ArenaVec<'_, T> {
+    fn drop(&mut self) {
+        self.clear();
+        if let Ok(layout) = Self::layout(self.capacity) {
+            unsafe {
+                self.arena.deallocate_raw(self.ptr.cast(), layout);
+            }
+        }
+    }
+}
+
+unsafe impl<T: Send> Send for ArenaVec<'_, T> {}
+unsafe impl<T: Sync> Sync for ArenaVec<'_, T> {}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaVec<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
@@ -0,0 +1,245 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::panic::Location;
+use libc::c_void;
+use super::super::spin::SpinLock;
+use super::StandardAllocator;
+
+// Same byte patterns as the MSVC debug heap, so they're easy to recognize in a memory dump
+const GUARD_BYTE: u8 = 0xFD;
+const CLEAN_BYTE: u8 = 0xCD;
+const FREED_BYTE: u8 = 0xDD;
+
+const GUARD_SIZE: usize = 0x10;
+const DEFAULT_ALIGNMENT: usize = 0x10;
+// How many freed addresses are remembered for telling a double free apart from a bogus pointer
+const RECENTLY_FREED_COUNT: usize = 64;
+
+#[derive(Copy, Clone)]
+struct Record {
+    size: usize,
+    // Bytes between the start of the underlying block and the returned address, front guard included
+    front: usize,
+    tag: &'static str,
+    location: &'static Location<'static>
+}
+
+struct State {
+    records: BTreeMap<usize, Record>,
+    recently_freed: [usize; RECENTLY_FREED_COUNT],
+    next_freed: usize
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct AllocationInfo {
+    pub address: usize,
+    pub size: usize,
+    pub tag: &'static str,
+    pub location: &'static Location<'static>
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum DebugError {
+    DoubleFree(usize),
+    // The address was never returned by this allocator
+    UnknownPointer(usize),
+    // The guard bytes in front of the block were overwritten
+    BufferUnderflow(AllocationInfo),
+    // The guard bytes after the block were overwritten
+    BufferOverflow(AllocationInfo)
+}
+
+// Wraps a StandardAllocator to catch heap corruption: every block is surrounded by guard bytes that are
+// checked when it is freed, freed memory is poisoned, and the size and caller of every live allocation is
+// kept in a side table so leaks can be listed. Allocations are bigger and slower, so only use it while debugging.
+pub struct DebugAllocator<'a> {
+    allocator: &'a StandardAllocator,
+    state: SpinLock<State>
+}
+
+impl AllocationInfo {
+    fn new(address: usize, record: &Record) -> Self {
+        Self {
+            address,
+            size: record.size,
+            tag: record.tag,
+            location: record.location
+        }
+    }
+}
+
+impl<'a> DebugAllocator<'a> {
+    pub const fn new(allocator: &'a StandardAllocator) -> Self {
+        Self {
+            allocator,
+            state: SpinLock::new(State {
+                records: BTreeMap::new(),
+                recently_freed: [0; RECENTLY_FREED_COUNT],
+                next_freed: 0
+            })
+        }
+    }
+
+    pub const fn allocator(&self) -> &'a StandardAllocator {
+        self.allocator
+    }
+
+    #[track_caller]
+    pub fn alloc(&self, size: usize) -> *mut c_void {
+        self.alloc_tagged(size, DEFAULT_ALIGNMENT, "")
+    }
+
+    #[track_caller]
+    pub fn alloc_aligned(&self, size: usize, alignment: usize) -> *mut c_void {
+        self.alloc_tagged(size, alignment, "")
+    }
+
+    // The tag shows up in reports next to the caller's location, e.g. the name of the asset being loaded
+    #[track_caller]
+    pub fn alloc_tagged(&self, size: usize, alignment: usize, tag: &'static str) -> *mut c_void {
+        let location = Location::caller();
+        if !alignment.is_power_of_two() {
+            return core::ptr::null_mut();
+        }
+        let alignment = core::cmp::max(alignment, DEFAULT_ALIGNMENT);
+        // The front guard is widened to the alignment so the returned address stays aligned
+        let front = core::cmp::max(GUARD_SIZE, alignment);
+        let total = match size.checked_add(front + GUARD_SIZE) {
+            Some(total) => total,
+            None => return core::ptr::null_mut()
+        };
+        let base = self.allocator.alloc_aligned(total, alignment) as *mut u8;
+        if base.is_null() {
+            return core::ptr::null_mut();
+        }
+
+        unsafe {
+            core::ptr::write_bytes(base, GUARD_BYTE, front);
+            core::ptr::write_bytes(base.add(front), CLEAN_BYTE, size);
+            core::ptr::write_bytes(base.add(front + size), GUARD_BYTE, GUARD_SIZE);
+        }
+        let address = base as usize + front;
+        self.state.lock().records.insert(address, Record {
+            size,
+            front,
+            tag,
+            location
+        });
+        address as *mut c_void
+    }
+
+    // Checks the block's guard bytes, poisons it and gives it back to the StandardAllocator.
+    // A block with damaged guards is still freed, the error says which allocation was overrun.
+    pub fn free(&self, address: *mut c_void) -> Result<(), DebugError> {
+        if address.is_null() {
+            return Ok(());
+        }
+        let address = address as usize;
+        let record = {
+            let mut state = self.state.lock();
+            match state.records.remove(&address) {
+                Some(record) => {
+                    let index = state.next_freed;
+                    state.recently_freed[index] = address;
+                    state.next_freed = (index + 1) % RECENTLY_FREED_COUNT;
+                    record
+                },
+                None if state.recently_freed.contains(&address) => return Err(DebugError::DoubleFree(address)),
+                None => return Err(DebugError::UnknownPointer(address))
+            }
+        };
+
+        let result = check_guards(address, &record);
+        unsafe {
+            let base = (address - record.front) as *mut u8;
+            core::ptr::write_bytes(base, FREED_BYTE, record.front + record.size + GUARD_SIZE);
+            self.allocator.free(base as *mut c_void);
+        }
+        result
+    }
+
+    // Always moves the block so stale pointers to the old one hit poisoned memory
+    #[track_caller]
+    pub fn realloc(&self, address: *mut c_void, new_size: usize) -> Result<*mut c_void, DebugError> {
+        if address.is_null() {
+            return Ok(self.alloc(new_size));
+        }
+        let record = match self.state.lock().records.get(&(address as usize)) {
+            Some(record) => *record,
+            None => return Err(DebugError::UnknownPointer(address as usize))
+        };
+        let alignment = core::cmp::max(record.front, DEFAULT_ALIGNMENT);
+        let new = self.alloc_tagged(new_size, alignment, record.tag);
+        if new.is_null() {
+            return Ok(new);
+        }
+        unsafe {
+            core::ptr::copy_nonoverlapping(address as *const u8, new as *mut u8, core::cmp::min(record.size, new_size));
+        }
+        self.free(address).map(|_| new)
+    }
+
+    // Size requested for a live allocation
+    pub fn size_of(&self, address: *const c_void) -> Option<usize> {
+        self.state.lock().records.get(&(address as usize)).map(|record| record.size)
+    }
+
+    // Checks the guard bytes of every live allocation and returns the first damaged one
+    pub fn check(&self) -> Result<(), DebugError> {
+        let state = self.state.lock();
+        for (&address, record) in state.records.iter() {
+            check_guards(address, record)?;
+        }
+        Ok(())
+    }
+
+    pub fn outstanding_count(&self) -> usize {
+        self.state.lock().records.len()
+    }
+
+    pub fn outstanding_size(&self) -> usize {
+        self.state.lock().records.values().map(|record| record.size).sum()
+    }
+
+    // Every allocation that hasn't been freed yet, ordered by address
+    pub fn outstanding(&self) -> Vec<AllocationInfo> {
+        let state = self.state.lock();
+        state.records.iter().map(|(&address, record)| AllocationInfo::new(address, record)).collect()
+    }
+}
+
+fn check_guards(address: usize, record: &Record) -> Result<(), DebugError> {
+    unsafe {
+        let front = core::slice::from_raw_parts((address - GUARD_SIZE) as *const u8, GUARD_SIZE);
+        if front.iter().any(|&byte| byte != GUARD_BYTE) {
+            return Err(DebugError::BufferUnderflow(AllocationInfo::new(address, record)));
+        }
+        let back = core::slice::from_raw_parts((address + record.size) as *const u8, GUARD_SIZE);
+        if back.iter().any(|&byte| byte != GUARD_BYTE) {
+            return Err(DebugError::BufferOverflow(AllocationInfo::new(address, record)));
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for AllocationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x} ({:#x} bytes", self.address, self.size)?;
+        if !self.tag.is_empty() {
+            write!(f, ", {}", self.tag)?;
+        }
+        write!(f, ") allocated at {}", self.location)
+    }
+}
+
+impl fmt::Display for DebugError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DebugError::DoubleFree(address) => write!(f, "double free of {:#x}", address),
+            DebugError::UnknownPointer(address) => write!(f, "{:#x} was not allocated by this allocator", address),
+            DebugError::BufferUnderflow(info) => write!(f, "buffer underflow in {}", info),
+            DebugError::BufferOverflow(info) => write!(f, "buffer overflow in {}", info)
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use core::alloc::{GlobalAlloc, Layout};
+use libc::c_void;
+use super::super::os;
+use super::super::spin::SpinLock;
+use super::StandardAllocator;
+
+// Alignment StandardAllocator::alloc already guarantees, anything stricter goes through alloc_aligned
+const DEFAULT_ALIGNMENT: usize = 0x10;
+
+enum State {
+    Uninitialized,
+    Ready(StandardAllocator),
+    // alloc_from_heap failed, every allocation fails from then on instead of retrying
+    Failed
+}
+
+// Global allocator managing a private block of the memory heap with a StandardAllocator.
+// The block is only taken from the heap on the first allocation, so this can be a static:
+//
+//     #[global_allocator]
+//     static ALLOCATOR: NnGlobalAllocator = NnGlobalAllocator::new(0x400000);
+//
+// Nothing here sizes the memory heap itself. Unless the process already set it up, call os::set_heap_size
+// with room for heap_size before anything allocates. Otherwise the block can't be taken and every allocation
+// returns null for good.
+pub struct NnGlobalAllocator {
+    heap_size: usize,
+    state: SpinLock<State>
+}
+
+impl NnGlobalAllocator {
+    // heap_size is rounded up to os::MEMORY_BLOCK_UNIT_SIZE
+    pub const fn new(heap_size: usize) -> Self {
+        let unit = os::MEMORY_BLOCK_UNIT_SIZE;
+        Self {
+            heap_size: heap_size.div_ceil(unit) * unit,
+            state: SpinLock::new(State::Uninitialized)
+        }
+    }
+
+    pub const fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    // Runs f with the underlying allocator, initializing it if needed. Returns None if the heap block couldn't be allocated.
+    // f must not allocate through this allocator, the lock isn't reentrant.
+    pub fn with_allocator<R, F: FnOnce(&mut StandardAllocator) -> R>(&self, f: F) -> Option<R> {
+        let mut state = self.state.lock();
+        if let State::Uninitialized = *state {
+            *state = match os::alloc_from_heap(self.heap_size) {
+                Ok(memory) => State::Ready(StandardAllocator::new_init(memory, self.heap_size)),
+                Err(_) => State::Failed
+            };
+        }
+        match &mut *state {
+            State::Ready(allocator) => Some(f(allocator)),
+            _ => None
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for NnGlobalAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.with_allocator(|allocator| {
+            if layout.align() <= DEFAULT_ALIGNMENT {
+                allocator.alloc(layout.size())
+            } else {
+                allocator.alloc_aligned(layout.size(), layout.align())
+            }
+        }).unwrap_or(core::ptr::null_mut()) as *mut u8
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        self.with_allocator(|allocator| allocator.free(ptr as *mut c_void));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.with_allocator(|allocator| {
+            if layout.align() <= DEFAULT_ALIGNMENT {
+                return allocator.realloc(ptr as *mut c_void, new_size);
+            }
+
+            // StandardAllocator::realloc only keeps the default alignment, so over-aligned blocks are moved by hand
+            let new = allocator.alloc_aligned(new_size, layout.align());
+            if !new.is_null() {
+                core::ptr::copy_nonoverlapping(ptr, new as *mut u8, core::cmp::min(layout.size(), new_size));
+                allocator.free(ptr as *mut c_void);
+            }
+            new
+        }).unwrap_or(core::ptr::null_mut()) as *mut u8
+    }
+}
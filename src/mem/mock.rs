@@ -0,0 +1,437 @@
+// Host implementation of the nn::mem::StandardAllocator symbols, enabled with the mock_mem feature.
+// Blocks are carved first-fit out of the region given to Initialize, the bookkeeping lives on the
+// host heap so the region itself only ever holds the caller's data.
+#![allow(non_snake_case)]
+
+use std::boxed::Box;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::vec::Vec;
+
+use libc::*;
+use super::{AllocatorHash, AllocatorWalkCallback, StandardAllocator};
+
+const DEFAULT_ALIGNMENT: usize = 0x10;
+
+// The first word of the SDK's 0x38 bytes holds the host-side state, so the allocator can still be moved around
+#[repr(C)]
+struct MockStandardAllocator {
+    heap: *mut Mutex<Heap>,
+    _x8: [u8; 0x30]
+}
+
+const _: () = assert!(core::mem::size_of::<MockStandardAllocator>() == core::mem::size_of::<StandardAllocator>());
+
+struct Heap {
+    base: usize,
+    size: usize,
+    // Free chunks by start address, adjacent chunks are always merged
+    free: BTreeMap<usize, usize>,
+    // Allocated blocks by address, sizes are rounded up to the default alignment
+    blocks: BTreeMap<usize, usize>
+}
+
+const fn align_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+impl Heap {
+    fn new(memory: usize, size: usize) -> Self {
+        let base = align_up(memory, DEFAULT_ALIGNMENT);
+        let size = (size - core::cmp::min(size, base - memory)) & !(DEFAULT_ALIGNMENT - 1);
+        let mut free = BTreeMap::new();
+        if size != 0 {
+            free.insert(base, size);
+        }
+        Self {
+            base,
+            size,
+            free,
+            blocks: BTreeMap::new()
+        }
+    }
+
+    fn allocate(&mut self, size: usize, alignment: usize) -> *mut c_void {
+        if !alignment.is_power_of_two() {
+            return core::ptr::null_mut();
+        }
+        let alignment = core::cmp::max(alignment, DEFAULT_ALIGNMENT);
+        let size = match size.checked_add(DEFAULT_ALIGNMENT - 1) {
+            Some(size) => core::cmp::max(size & !(DEFAULT_ALIGNMENT - 1), DEFAULT_ALIGNMENT),
+            None => return core::ptr::null_mut()
+        };
+
+        let found = self.free.iter().find_map(|(&start, &chunk_size)| {
+            let address = align_up(start, alignment);
+            let end = start + chunk_size;
+            if address >= end || end - address < size {
+                None
+            } else {
+                Some((start, chunk_size, address))
+            }
+        });
+        let (start, chunk_size, address) = match found {
+            Some(found) => found,
+            None => return core::ptr::null_mut()
+        };
+
+        // Alignment padding in front of the block and whatever is left after it go back into the free list
+        self.free.remove(&start);
+        if address != start {
+            self.free.insert(start, address - start);
+        }
+        let end = start + chunk_size;
+        if end != address + size {
+            self.free.insert(address + size, end - (address + size));
+        }
+        self.blocks.insert(address, size);
+        address as *mut c_void
+    }
+
+    fn release(&mut self, start: usize, size: usize) {
+        let mut start = start;
+        let mut size = size;
+        if let Some((&prev_start, &prev_size)) = self.free.range(..start).next_back() {
+            if prev_start + prev_size == start {
+                self.free.remove(&prev_start);
+                start = prev_start;
+                size += prev_size;
+            }
+        }
+        if let Some(&next_size) = self.free.get(&(start + size)) {
+            self.free.remove(&(start + size));
+            size += next_size;
+        }
+        self.free.insert(start, size);
+    }
+
+    fn size_of(&self, address: usize) -> usize {
+        match self.blocks.get(&address) {
+            Some(size) => *size,
+            None => panic!("nn::mem::StandardAllocator: {:#x} was not allocated by this allocator", address)
+        }
+    }
+
+    fn free(&mut self, address: usize) {
+        let size = self.size_of(address);
+        self.blocks.remove(&address);
+        self.release(address, size);
+    }
+
+    fn reallocate(&mut self, address: usize, new_size: usize) -> *mut c_void {
+        let size = self.size_of(address);
+        let new_size = match new_size.checked_add(DEFAULT_ALIGNMENT - 1) {
+            Some(size) => size & !(DEFAULT_ALIGNMENT - 1),
+            None => return core::ptr::null_mut()
+        };
+
+        // Shrinking and growing into a free neighbour are done in place
+        let end = address + size;
+        let following = self.free.get(&end).copied().unwrap_or(0);
+        if new_size <= size + following {
+            if following != 0 {
+                self.free.remove(&end);
+            }
+            if size + following > new_size {
+                self.release(address + new_size, size + following - new_size);
+            }
+            self.blocks.insert(address, new_size);
+            return address as *mut c_void;
+        }
+
+        let new = self.allocate(new_size, DEFAULT_ALIGNMENT);
+        if !new.is_null() {
+            unsafe {
+                core::ptr::copy_nonoverlapping(address as *const u8, new as *mut u8, core::cmp::min(size, new_size));
+            }
+            self.free(address);
+        }
+        new
+    }
+
+    fn hash(&self) -> AllocatorHash {
+        // FNV-1a over every block's address and size
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for (&address, &size) in self.blocks.iter() {
+            for value in [address as u64, size as u64].iter() {
+                for byte in value.to_le_bytes().iter() {
+                    hash ^= *byte as u64;
+                    hash = hash.wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+        AllocatorHash {
+            regions: self.blocks.len(),
+            total_size: self.blocks.values().sum(),
+            hash: hash as usize
+        }
+    }
+}
+
+unsafe fn heap<'a>(this: *const StandardAllocator) -> Option<&'a Mutex<Heap>> {
+    let heap = (*(this as *const MockStandardAllocator)).heap;
+    if heap.is_null() {
+        None
+    } else {
+        Some(&*heap)
+    }
+}
+
+unsafe fn with_heap<T, F: FnOnce(&mut Heap) -> T>(this: *const StandardAllocator, f: F) -> T {
+    match heap(this) {
+        Some(heap) => f(&mut heap.lock().unwrap_or_else(|e| e.into_inner())),
+        None => panic!("nn::mem::StandardAllocator: allocator {:p} is not initialized", this)
+    }
+}
+
+pub unsafe fn AlignedAllocate(this: *mut StandardAllocator, size: usize, alignment: usize) -> *mut c_void {
+    with_heap(this, |heap| heap.allocate(size, alignment))
+}
+
+pub unsafe fn Allocate(this: *mut StandardAllocator, size: usize) -> *mut c_void {
+    AlignedAllocate(this, size, DEFAULT_ALIGNMENT)
+}
+
+pub unsafe fn CleanUpManagementArea(_this: *const StandardAllocator) {
+}
+
+pub unsafe fn ClearThreadCache(_this: *const StandardAllocator) {
+}
+
+pub unsafe fn Dump(this: *const StandardAllocator) {
+    with_heap(this, |heap| {
+        std::eprintln!("StandardAllocator {:p}: region {:#x}..{:#x}", this, heap.base, heap.base + heap.size);
+        for (address, size) in heap.blocks.iter() {
+            std::eprintln!("  {:#x} {:#x}", address, size);
+        }
+    })
+}
+
+// Finalizing an allocator that was never initialized does nothing, StandardAllocator::default relies on it
+pub unsafe fn Finalize(this: *mut StandardAllocator) {
+    let this = &mut *(this as *mut MockStandardAllocator);
+    if !this.heap.is_null() {
+        drop(Box::from_raw(this.heap));
+        this.heap = core::ptr::null_mut();
+    }
+}
+
+pub unsafe fn Free(this: *mut StandardAllocator, address: *mut c_void) {
+    if address.is_null() {
+        return;
+    }
+    with_heap(this, |heap| heap.free(address as usize))
+}
+
+// Largest block that can currently be allocated with the default alignment
+pub unsafe fn GetAllocatableSize(this: *const StandardAllocator) -> usize {
+    with_heap(this, |heap| heap.free.values().copied().max().unwrap_or(0))
+}
+
+pub unsafe fn GetSizeOf(this: *const StandardAllocator, address: *const c_void) -> usize {
+    with_heap(this, |heap| heap.size_of(address as usize))
+}
+
+pub unsafe fn GetTotalFreeSize(this: *const StandardAllocator) -> usize {
+    with_heap(this, |heap| heap.free.values().sum())
+}
+
+pub unsafe fn Hash(this: *const StandardAllocator) -> AllocatorHash {
+    with_heap(this, |heap| heap.hash())
+}
+
+pub unsafe fn Initialize(this: *mut StandardAllocator, memory: *mut c_void, size: usize) {
+    if heap(this).is_some() {
+        panic!("nn::mem::StandardAllocator: allocator {:p} is already initialized", this);
+    }
+    if memory.is_null() {
+        panic!("nn::mem::StandardAllocator: null memory region");
+    }
+    let heap = Box::new(Mutex::new(Heap::new(memory as usize, size)));
+    (*(this as *mut MockStandardAllocator)).heap = Box::into_raw(heap);
+}
+
+// The host has no thread caches, so this is the same as Initialize
+pub unsafe fn InitializeCached(this: *mut StandardAllocator, memory: *mut c_void, size: usize, _is_cache_enable: bool) {
+    Initialize(this, memory, size)
+}
+
+pub unsafe fn Reallocate(this: *mut StandardAllocator, address: *mut c_void, new_size: usize) -> *mut c_void {
+    if address.is_null() {
+        return Allocate(this, new_size);
+    }
+    if new_size == 0 {
+        Free(this, address);
+        return core::ptr::null_mut();
+    }
+    with_heap(this, |heap| heap.reallocate(address as usize, new_size))
+}
+
+pub unsafe fn Constructor(this: *mut StandardAllocator) {
+    core::ptr::write_bytes(this as *mut MockStandardAllocator, 0, 1);
+}
+
+pub unsafe fn Constructor_Initialize(this: *mut StandardAllocator, memory: *mut c_void, size: usize) {
+    Constructor(this);
+    Initialize(this, memory, size)
+}
+
+pub unsafe fn Constructor_InitializeCached(this: *mut StandardAllocator, memory: *mut c_void, size: usize, is_cache_enable: bool) {
+    Constructor(this);
+    InitializeCached(this, memory, size, is_cache_enable)
+}
+
+// The callback runs without the allocator locked, so it may allocate and free
+pub unsafe fn WalkAllocatedBlocks(this: *const StandardAllocator, callback: AllocatorWalkCallback, user_data: *mut c_void) {
+    let blocks: Vec<(usize, usize)> = with_heap(this, |heap| {
+        heap.blocks.iter().map(|(&address, &size)| (address, size)).collect()
+    });
+    for (address, size) in blocks {
+        if !callback(address as *mut c_void, size, user_data) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+    use super::super::{HashDiff, HeapReport, StandardAllocator};
+
+    const REGION_SIZE: usize = 0x10000;
+
+    // u128 keeps the region 16-byte aligned, so the whole of it is usable
+    fn region() -> Vec<u128> {
+        std::vec![0; REGION_SIZE / 16]
+    }
+
+    #[test]
+    fn allocate_free_and_reallocate() {
+        let mut memory = region();
+        let allocator = StandardAllocator::new_init(memory.as_mut_ptr() as _, REGION_SIZE);
+
+        let block = allocator.alloc(100) as *mut u8;
+        assert!(!block.is_null());
+        assert_eq!(allocator.get_size_of_block(block as _), 0x70);
+        unsafe {
+            for i in 0..100 {
+                *block.add(i) = i as u8;
+            }
+        }
+
+        // The neighbour is free, so growing stays in place
+        let grown = allocator.realloc(block as _, 0x200) as *mut u8;
+        assert_eq!(grown, block);
+        assert_eq!(allocator.get_size_of_block(grown as _), 0x200);
+
+        // With the neighbour taken it has to move, and the contents come along
+        let blocker = allocator.alloc(0x10);
+        let moved = allocator.realloc(grown as _, 0x1000) as *mut u8;
+        assert_ne!(moved, grown);
+        unsafe {
+            assert!((0..100).all(|i| *moved.add(i) == i as u8));
+        }
+
+        let shrunk = allocator.realloc(moved as _, 0x20) as *mut u8;
+        assert_eq!(shrunk, moved);
+        assert_eq!(allocator.get_size_of_block(shrunk as _), 0x20);
+
+        allocator.free(shrunk as _);
+        allocator.free(blocker);
+        allocator.free(core::ptr::null_mut());
+        assert_eq!(allocator.get_hash().regions, 0);
+        assert!(allocator.alloc(REGION_SIZE + 1).is_null());
+    }
+
+    #[test]
+    fn aligned_allocation() {
+        let mut memory = region();
+        let allocator = StandardAllocator::new_init(memory.as_mut_ptr() as _, REGION_SIZE);
+
+        let small = allocator.alloc(1);
+        assert_eq!(small as usize % 0x10, 0);
+        for &alignment in [0x20, 0x100, 0x1000].iter() {
+            let block = allocator.alloc_aligned(0x30, alignment);
+            assert!(!block.is_null());
+            assert_eq!(block as usize % alignment, 0);
+            allocator.free(block);
+        }
+        assert!(allocator.alloc_aligned(0x10, 0x30).is_null());
+        allocator.free(small);
+        assert_eq!(allocator.get_total_free_size(), REGION_SIZE);
+    }
+
+    #[test]
+    fn free_space_accounting() {
+        let mut memory = region();
+        let allocator = StandardAllocator::new_init(memory.as_mut_ptr() as _, REGION_SIZE);
+        assert_eq!(allocator.get_total_free_size(), REGION_SIZE);
+        assert_eq!(allocator.get_allocatable_size(), REGION_SIZE);
+
+        let a = allocator.alloc(0x100);
+        let b = allocator.alloc(0x1000);
+        let c = allocator.alloc(0x100);
+        assert_eq!(allocator.get_total_free_size(), REGION_SIZE - 0x1200);
+        assert_eq!(allocator.get_allocatable_size(), REGION_SIZE - 0x1200);
+
+        // A hole in the middle counts towards the total, but isn't contiguous with the rest
+        allocator.free(b);
+        assert_eq!(allocator.get_total_free_size(), REGION_SIZE - 0x200);
+        assert_eq!(allocator.get_allocatable_size(), REGION_SIZE - 0x1200);
+
+        // Freeing the rest merges everything back into one chunk
+        allocator.free(a);
+        allocator.free(c);
+        assert_eq!(allocator.get_total_free_size(), REGION_SIZE);
+        assert_eq!(allocator.get_allocatable_size(), REGION_SIZE);
+    }
+
+    #[test]
+    fn walk_and_hash() {
+        let mut memory = region();
+        let allocator = StandardAllocator::new_init(memory.as_mut_ptr() as _, REGION_SIZE);
+        let a = allocator.alloc(0x10);
+        let b = allocator.alloc(0x20);
+
+        let mut blocks = Vec::new();
+        allocator.for_each_allocated_block(|address, size| {
+            blocks.push((address, size));
+            true
+        });
+        assert_eq!(blocks, [(a, 0x10), (b, 0x20)]);
+        let hash = allocator.get_hash();
+        assert_eq!((hash.regions, hash.total_size), (2, 0x30));
+
+        allocator.free(b);
+        assert_ne!(allocator.get_hash().hash, hash.hash);
+        allocator.free(a);
+    }
+
+    #[test]
+    fn heap_report() {
+        let mut memory = region();
+        let allocator = StandardAllocator::new_init(memory.as_mut_ptr() as _, REGION_SIZE);
+        let a = allocator.alloc(0x10);
+        let before = HeapReport::capture_with_blocks(&allocator, Vec::with_capacity(4));
+        let b = allocator.alloc(0x1000);
+        let c = allocator.alloc(0x20);
+
+        let report = allocator.report();
+        assert_eq!((report.block_count, report.allocated_size, report.largest_block), (3, 0x1030, 0x1000));
+        assert_eq!(report.histogram[0], 1);
+        assert_eq!(report.histogram[1], 1);
+        assert_eq!(report.histogram[8], 1);
+        assert!(report.blocks.is_empty());
+        assert_eq!(report.diff(&before), HashDiff { regions: 2, total_size: 0x1020, changed: true });
+
+        // Blocks past the buffer's capacity are left out rather than allocated for
+        let after = HeapReport::capture_with_blocks(&allocator, Vec::with_capacity(2));
+        assert_eq!(after.blocks, [(a as usize, 0x10), (b as usize, 0x1000)]);
+        assert_eq!(after.new_blocks(&before).collect::<Vec<_>>(), [(b as usize, 0x1000)]);
+
+        allocator.free(b);
+        allocator.free(c);
+        assert!(allocator.report().diff(&before).is_unchanged());
+        allocator.free(a);
+    }
+}
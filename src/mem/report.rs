@@ -0,0 +1,175 @@
+use alloc::vec::Vec;
+use core::fmt;
+use super::{AllocatorHash, StandardAllocator};
+
+// Block sizes are bucketed by powers of two, from <= 0x10 up to <= 0x100000 plus one bucket for anything bigger
+const HISTOGRAM_MIN_SHIFT: u32 = 4;
+const HISTOGRAM_BUCKETS: usize = 18;
+
+// Snapshot of a StandardAllocator's usage, the Display output is meant to be written to a log file
+#[derive(Clone)]
+pub struct HeapReport {
+    pub block_count: usize,
+    pub allocated_size: usize,
+    pub largest_block: usize,
+    pub total_free_size: usize,
+    // Largest block that could currently be allocated
+    pub allocatable_size: usize,
+    pub histogram: [usize; HISTOGRAM_BUCKETS],
+    pub hash: AllocatorHash,
+    // Address and size of every allocated block, only filled in by capture_with_blocks
+    pub blocks: Vec<(usize, usize)>
+}
+
+// Change between two AllocatorHash snapshots, positive values mean the heap grew
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HashDiff {
+    pub regions: isize,
+    pub total_size: isize,
+    // The set of blocks differs even if the counts happen to match
+    pub changed: bool
+}
+
+fn histogram_bucket(size: usize) -> usize {
+    let shift = (usize::BITS - size.saturating_sub(1).leading_zeros()).saturating_sub(HISTOGRAM_MIN_SHIFT);
+    core::cmp::min(shift as usize, HISTOGRAM_BUCKETS - 1)
+}
+
+impl AllocatorHash {
+    pub fn diff(&self, earlier: &AllocatorHash) -> HashDiff {
+        HashDiff {
+            regions: self.regions as isize - earlier.regions as isize,
+            total_size: self.total_size as isize - earlier.total_size as isize,
+            changed: self != earlier
+        }
+    }
+}
+
+impl HashDiff {
+    pub fn is_unchanged(&self) -> bool {
+        !self.changed
+    }
+}
+
+impl HeapReport {
+    pub const HISTOGRAM_BUCKETS: usize = HISTOGRAM_BUCKETS;
+
+    // Upper bound of a histogram bucket, None for the last one
+    pub const fn histogram_bucket_limit(bucket: usize) -> Option<usize> {
+        if bucket + 1 < HISTOGRAM_BUCKETS {
+            Some(1 << (bucket as u32 + HISTOGRAM_MIN_SHIFT))
+        } else {
+            None
+        }
+    }
+
+    // Doesn't allocate, so it can run inside NnGlobalAllocator::with_allocator
+    pub fn capture(allocator: &StandardAllocator) -> Self {
+        let mut report = Self {
+            block_count: 0,
+            allocated_size: 0,
+            largest_block: 0,
+            total_free_size: allocator.get_total_free_size(),
+            allocatable_size: allocator.get_allocatable_size(),
+            histogram: [0; HISTOGRAM_BUCKETS],
+            hash: allocator.get_hash(),
+            blocks: Vec::new()
+        };
+        allocator.for_each_allocated_block(|_, size| {
+            report.block_count += 1;
+            report.allocated_size += size;
+            report.largest_block = core::cmp::max(report.largest_block, size);
+            report.histogram[histogram_bucket(size)] += 1;
+            true
+        });
+        report
+    }
+
+    // Also records every block into blocks, useful for diffing two reports to find what leaked.
+    // Nothing is allocated here: blocks is cleared and filled up to its capacity, so reserve room for every block
+    // first. That has to happen before taking NnGlobalAllocator::with_allocator's lock, growing the Vec inside it
+    // would deadlock when it's the global allocator.
+    pub fn capture_with_blocks(allocator: &StandardAllocator, mut blocks: Vec<(usize, usize)>) -> Self {
+        let mut report = Self::capture(allocator);
+        blocks.clear();
+        allocator.for_each_allocated_block(|address, size| {
+            if blocks.len() == blocks.capacity() {
+                return false;
+            }
+            blocks.push((address as usize, size));
+            true
+        });
+        report.blocks = blocks;
+        report
+    }
+
+    // 0.0 when all free memory is one contiguous block, approaching 1.0 as it gets split up
+    pub fn fragmentation(&self) -> f32 {
+        if self.total_free_size == 0 {
+            0.0
+        } else {
+            1.0 - self.allocatable_size as f32 / self.total_free_size as f32
+        }
+    }
+
+    pub fn diff(&self, earlier: &HeapReport) -> HashDiff {
+        self.hash.diff(&earlier.hash)
+    }
+
+    // Blocks in this report that weren't in earlier, both need to come from capture_with_blocks
+    pub fn new_blocks<'a>(&'a self, earlier: &'a HeapReport) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.blocks.iter().copied().filter(move |block| !earlier.blocks.contains(block))
+    }
+}
+
+impl fmt::Display for HashDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.changed {
+            return f.write_str("unchanged");
+        }
+        write!(f, "{:+} blocks, {:+} bytes", self.regions, self.total_size)
+    }
+}
+
+impl fmt::Display for HeapReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "allocated: {} blocks, {:#x} bytes (largest {:#x})", self.block_count, self.allocated_size, self.largest_block)?;
+        writeln!(f, "free: {:#x} bytes, largest allocatable {:#x} (fragmentation {:.1}%)", self.total_free_size, self.allocatable_size, self.fragmentation() * 100.0)?;
+        writeln!(f, "hash: {:#x}", self.hash.hash)?;
+        writeln!(f, "size histogram:")?;
+        for (bucket, &count) in self.histogram.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            match Self::histogram_bucket_limit(bucket) {
+                Some(limit) => writeln!(f, "  <= {:#x}: {}", limit, count)?,
+                None => writeln!(f, "   > {:#x}: {}", 1usize << (HISTOGRAM_BUCKETS as u32 - 2 + HISTOGRAM_MIN_SHIFT), count)?
+            }
+        }
+        if !self.blocks.is_empty() {
+            writeln!(f, "blocks:")?;
+            for (address, size) in self.blocks.iter() {
+                writeln!(f, "  {:#x} {:#x}", address, size)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HeapReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeapReport")
+            .field("block_count", &self.block_count)
+            .field("allocated_size", &self.allocated_size)
+            .field("total_free_size", &self.total_free_size)
+            .field("allocatable_size", &self.allocatable_size)
+            .field("fragmentation", &self.fragmentation())
+            .finish()
+    }
+}
+
+impl StandardAllocator {
+    pub fn report(&self) -> HeapReport {
+        HeapReport::capture(self)
+    }
+}
@@ -4,9 +4,22 @@ use libc::*;
 use super::Result as NxResult;
 use super::{c_str, get_rust_result};
 
+#[cfg(feature = "mock_os")]
+mod mock;
+#[cfg(feature = "mock_os")]
+use mock as os_impl;
+pub mod result;
+pub mod sync;
+pub mod thread;
+pub mod tls;
+
+#[cfg(not(feature = "mock_os"))]
 mod os_impl {
     use super::super::{TimeSpan, Result};
-    use super::{ThreadType, ThreadFn};
+    use super::{ConditionVariableType, EventType, LightEventType, MessageQueueType, MultiWaitHolderType, MultiWaitType, MutexType};
+    use super::{NativeHandle, SemaphoreType, SystemEventType, ThreadType, ThreadFn};
+    use super::sync::{EventClearMode, MessageQueueWaitType};
+    use super::tls::{TlsDestructor, TlsSlot};
     use libc::*;
 
     extern "C" {
@@ -99,6 +112,425 @@ mod os_impl {
         #[link_name = "\u{1}_ZN2nn2os20GetCurrentCoreNumberEv"]
         pub fn GetCurrentCoreNumber() -> i32;
 
+        // Thread local storage
+
+        #[link_name = "\u{1}_ZN2nn2os15AllocateTlsSlotEPNS0_7TlsSlotEPFvmE"]
+        pub fn AllocateTlsSlot(
+            slot: *mut TlsSlot,
+            destructor: Option<TlsDestructor>
+        ) -> Result;
+
+        #[link_name = "\u{1}_ZN2nn2os11FreeTlsSlotENS0_7TlsSlotE"]
+        pub fn FreeTlsSlot(
+            slot: TlsSlot
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os11GetTlsValueENS0_7TlsSlotE"]
+        pub fn GetTlsValue(
+            slot: TlsSlot
+        ) -> usize;
+
+        #[link_name = "\u{1}_ZN2nn2os11SetTlsValueENS0_7TlsSlotEm"]
+        pub fn SetTlsValue(
+            slot: TlsSlot,
+            value: usize
+        );
+
+        // Synchronization
+
+        #[link_name = "\u{1}_ZN2nn2os15InitializeMutexEPNS0_9MutexTypeEbi"]
+        pub fn InitializeMutex(
+            mutex: *mut MutexType,
+            is_recursive: bool,
+            lock_level: i32
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os13FinalizeMutexEPNS0_9MutexTypeE"]
+        pub fn FinalizeMutex(
+            mutex: *mut MutexType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os9LockMutexEPNS0_9MutexTypeE"]
+        pub fn LockMutex(
+            mutex: *mut MutexType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os12TryLockMutexEPNS0_9MutexTypeE"]
+        pub fn TryLockMutex(
+            mutex: *mut MutexType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os11UnlockMutexEPNS0_9MutexTypeE"]
+        pub fn UnlockMutex(
+            mutex: *mut MutexType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os28IsMutexLockedByCurrentThreadEPKNS0_9MutexTypeE"]
+        pub fn IsMutexLockedByCurrentThread(
+            mutex: *const MutexType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os27InitializeConditionVariableEPNS0_21ConditionVariableTypeE"]
+        pub fn InitializeConditionVariable(
+            condvar: *mut ConditionVariableType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os25FinalizeConditionVariableEPNS0_21ConditionVariableTypeE"]
+        pub fn FinalizeConditionVariable(
+            condvar: *mut ConditionVariableType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os23SignalConditionVariableEPNS0_21ConditionVariableTypeE"]
+        pub fn SignalConditionVariable(
+            condvar: *mut ConditionVariableType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os26BroadcastConditionVariableEPNS0_21ConditionVariableTypeE"]
+        pub fn BroadcastConditionVariable(
+            condvar: *mut ConditionVariableType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os21WaitConditionVariableEPNS0_21ConditionVariableTypeEPNS0_9MutexTypeE"]
+        pub fn WaitConditionVariable(
+            condvar: *mut ConditionVariableType,
+            mutex: *mut MutexType
+        );
+
+        // Returns one of the CONDITION_VARIABLE_STATUS values
+        #[link_name = "\u{1}_ZN2nn2os26TimedWaitConditionVariableEPNS0_21ConditionVariableTypeEPNS0_9MutexTypeENS_8TimeSpanE"]
+        pub fn TimedWaitConditionVariable(
+            condvar: *mut ConditionVariableType,
+            mutex: *mut MutexType,
+            timeout: TimeSpan
+        ) -> i32;
+
+        #[link_name = "\u{1}_ZN2nn2os15InitializeEventEPNS0_9EventTypeEbNS0_14EventClearModeE"]
+        pub fn InitializeEvent(
+            event: *mut EventType,
+            is_signaled: bool,
+            clear_mode: EventClearMode
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os13FinalizeEventEPNS0_9EventTypeE"]
+        pub fn FinalizeEvent(
+            event: *mut EventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os11SignalEventEPNS0_9EventTypeE"]
+        pub fn SignalEvent(
+            event: *mut EventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os9WaitEventEPNS0_9EventTypeE"]
+        pub fn WaitEvent(
+            event: *mut EventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os12TryWaitEventEPNS0_9EventTypeE"]
+        pub fn TryWaitEvent(
+            event: *mut EventType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os14TimedWaitEventEPNS0_9EventTypeENS_8TimeSpanE"]
+        pub fn TimedWaitEvent(
+            event: *mut EventType,
+            timeout: TimeSpan
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os10ClearEventEPNS0_9EventTypeE"]
+        pub fn ClearEvent(
+            event: *mut EventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os20InitializeLightEventEPNS0_14LightEventTypeEbNS0_14EventClearModeE"]
+        pub fn InitializeLightEvent(
+            event: *mut LightEventType,
+            is_signaled: bool,
+            clear_mode: EventClearMode
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os18FinalizeLightEventEPNS0_14LightEventTypeE"]
+        pub fn FinalizeLightEvent(
+            event: *mut LightEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os16SignalLightEventEPNS0_14LightEventTypeE"]
+        pub fn SignalLightEvent(
+            event: *mut LightEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os14WaitLightEventEPNS0_14LightEventTypeE"]
+        pub fn WaitLightEvent(
+            event: *mut LightEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os17TryWaitLightEventEPNS0_14LightEventTypeE"]
+        pub fn TryWaitLightEvent(
+            event: *mut LightEventType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os19TimedWaitLightEventEPNS0_14LightEventTypeENS_8TimeSpanE"]
+        pub fn TimedWaitLightEvent(
+            event: *mut LightEventType,
+            timeout: TimeSpan
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os15ClearLightEventEPNS0_14LightEventTypeE"]
+        pub fn ClearLightEvent(
+            event: *mut LightEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os17CreateSystemEventEPNS0_15SystemEventTypeENS0_14EventClearModeEb"]
+        pub fn CreateSystemEvent(
+            event: *mut SystemEventType,
+            clear_mode: EventClearMode,
+            interprocess: bool
+        ) -> Result;
+
+        #[link_name = "\u{1}_ZN2nn2os17AttachSystemEventEPNS0_15SystemEventTypeEjbjbNS0_14EventClearModeE"]
+        pub fn AttachSystemEvent(
+            event: *mut SystemEventType,
+            readable_handle: NativeHandle,
+            is_readable_handle_managed: bool,
+            writable_handle: NativeHandle,
+            is_writable_handle_managed: bool,
+            clear_mode: EventClearMode
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os33AttachReadableHandleToSystemEventEPNS0_15SystemEventTypeEjbNS0_14EventClearModeE"]
+        pub fn AttachReadableHandleToSystemEvent(
+            event: *mut SystemEventType,
+            readable_handle: NativeHandle,
+            is_managed: bool,
+            clear_mode: EventClearMode
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os18DestroySystemEventEPNS0_15SystemEventTypeE"]
+        pub fn DestroySystemEvent(
+            event: *mut SystemEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os17SignalSystemEventEPNS0_15SystemEventTypeE"]
+        pub fn SignalSystemEvent(
+            event: *mut SystemEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os15WaitSystemEventEPNS0_15SystemEventTypeE"]
+        pub fn WaitSystemEvent(
+            event: *mut SystemEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os18TryWaitSystemEventEPNS0_15SystemEventTypeE"]
+        pub fn TryWaitSystemEvent(
+            event: *mut SystemEventType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os20TimedWaitSystemEventEPNS0_15SystemEventTypeENS_8TimeSpanE"]
+        pub fn TimedWaitSystemEvent(
+            event: *mut SystemEventType,
+            timeout: TimeSpan
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os16ClearSystemEventEPNS0_15SystemEventTypeE"]
+        pub fn ClearSystemEvent(
+            event: *mut SystemEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os30GetReadableHandleOfSystemEventEPKNS0_15SystemEventTypeE"]
+        pub fn GetReadableHandleOfSystemEvent(
+            event: *const SystemEventType
+        ) -> NativeHandle;
+
+        #[link_name = "\u{1}_ZN2nn2os30GetWritableHandleOfSystemEventEPKNS0_15SystemEventTypeE"]
+        pub fn GetWritableHandleOfSystemEvent(
+            event: *const SystemEventType
+        ) -> NativeHandle;
+
+        #[link_name = "\u{1}_ZN2nn2os19InitializeSemaphoreEPNS0_13SemaphoreTypeEii"]
+        pub fn InitializeSemaphore(
+            semaphore: *mut SemaphoreType,
+            initial_count: i32,
+            max_count: i32
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os17FinalizeSemaphoreEPNS0_13SemaphoreTypeE"]
+        pub fn FinalizeSemaphore(
+            semaphore: *mut SemaphoreType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os16AcquireSemaphoreEPNS0_13SemaphoreTypeE"]
+        pub fn AcquireSemaphore(
+            semaphore: *mut SemaphoreType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os19TryAcquireSemaphoreEPNS0_13SemaphoreTypeE"]
+        pub fn TryAcquireSemaphore(
+            semaphore: *mut SemaphoreType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os21TimedAcquireSemaphoreEPNS0_13SemaphoreTypeENS_8TimeSpanE"]
+        pub fn TimedAcquireSemaphore(
+            semaphore: *mut SemaphoreType,
+            timeout: TimeSpan
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os16ReleaseSemaphoreEPNS0_13SemaphoreTypeE"]
+        pub fn ReleaseSemaphore(
+            semaphore: *mut SemaphoreType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os16ReleaseSemaphoreEPNS0_13SemaphoreTypeEi"]
+        pub fn ReleaseSemaphoreCount(
+            semaphore: *mut SemaphoreType,
+            count: i32
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os24GetCurrentSemaphoreCountEPKNS0_13SemaphoreTypeE"]
+        pub fn GetCurrentSemaphoreCount(
+            semaphore: *const SemaphoreType
+        ) -> i32;
+
+        #[link_name = "\u{1}_ZN2nn2os22InitializeMessageQueueEPNS0_16MessageQueueTypeEPmm"]
+        pub fn InitializeMessageQueue(
+            queue: *mut MessageQueueType,
+            buffer: *mut usize,
+            count: usize
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os20FinalizeMessageQueueEPNS0_16MessageQueueTypeE"]
+        pub fn FinalizeMessageQueue(
+            queue: *mut MessageQueueType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os16SendMessageQueueEPNS0_16MessageQueueTypeEm"]
+        pub fn SendMessageQueue(
+            queue: *mut MessageQueueType,
+            data: usize
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os19TrySendMessageQueueEPNS0_16MessageQueueTypeEm"]
+        pub fn TrySendMessageQueue(
+            queue: *mut MessageQueueType,
+            data: usize
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os21TimedSendMessageQueueEPNS0_16MessageQueueTypeEmNS_8TimeSpanE"]
+        pub fn TimedSendMessageQueue(
+            queue: *mut MessageQueueType,
+            data: usize,
+            timeout: TimeSpan
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os19ReceiveMessageQueueEPmPNS0_16MessageQueueTypeE"]
+        pub fn ReceiveMessageQueue(
+            out: *mut usize,
+            queue: *mut MessageQueueType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os22TryReceiveMessageQueueEPmPNS0_16MessageQueueTypeE"]
+        pub fn TryReceiveMessageQueue(
+            out: *mut usize,
+            queue: *mut MessageQueueType
+        ) -> bool;
+
+        #[link_name = "\u{1}_ZN2nn2os24TimedReceiveMessageQueueEPmPNS0_16MessageQueueTypeENS_8TimeSpanE"]
+        pub fn TimedReceiveMessageQueue(
+            out: *mut usize,
+            queue: *mut MessageQueueType,
+            timeout: TimeSpan
+        ) -> bool;
+
+        // Multi wait
+
+        #[link_name = "\u{1}_ZN2nn2os19InitializeMultiWaitEPNS0_13MultiWaitTypeE"]
+        pub fn InitializeMultiWait(
+            multi_wait: *mut MultiWaitType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os17FinalizeMultiWaitEPNS0_13MultiWaitTypeE"]
+        pub fn FinalizeMultiWait(
+            multi_wait: *mut MultiWaitType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os25InitializeMultiWaitHolderEPNS0_19MultiWaitHolderTypeEPNS0_9EventTypeE"]
+        pub fn InitializeMultiWaitHolderEvent(
+            holder: *mut MultiWaitHolderType,
+            event: *mut EventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os25InitializeMultiWaitHolderEPNS0_19MultiWaitHolderTypeEPNS0_15SystemEventTypeE"]
+        pub fn InitializeMultiWaitHolderSystemEvent(
+            holder: *mut MultiWaitHolderType,
+            event: *mut SystemEventType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os25InitializeMultiWaitHolderEPNS0_19MultiWaitHolderTypeEPNS0_10ThreadTypeE"]
+        pub fn InitializeMultiWaitHolderThread(
+            holder: *mut MultiWaitHolderType,
+            thread: *mut ThreadType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os25InitializeMultiWaitHolderEPNS0_19MultiWaitHolderTypeEPNS0_13SemaphoreTypeE"]
+        pub fn InitializeMultiWaitHolderSemaphore(
+            holder: *mut MultiWaitHolderType,
+            semaphore: *mut SemaphoreType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os25InitializeMultiWaitHolderEPNS0_19MultiWaitHolderTypeEPNS0_16MessageQueueTypeENS0_20MessageQueueWaitTypeE"]
+        pub fn InitializeMultiWaitHolderMessageQueue(
+            holder: *mut MultiWaitHolderType,
+            queue: *mut MessageQueueType,
+            wait_type: MessageQueueWaitType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os23FinalizeMultiWaitHolderEPNS0_19MultiWaitHolderTypeE"]
+        pub fn FinalizeMultiWaitHolder(
+            holder: *mut MultiWaitHolderType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os19LinkMultiWaitHolderEPNS0_13MultiWaitTypeEPNS0_19MultiWaitHolderTypeE"]
+        pub fn LinkMultiWaitHolder(
+            multi_wait: *mut MultiWaitType,
+            holder: *mut MultiWaitHolderType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os21UnlinkMultiWaitHolderEPNS0_19MultiWaitHolderTypeE"]
+        pub fn UnlinkMultiWaitHolder(
+            holder: *mut MultiWaitHolderType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os24UnlinkAllMultiWaitHolderEPNS0_13MultiWaitTypeE"]
+        pub fn UnlinkAllMultiWaitHolder(
+            multi_wait: *mut MultiWaitType
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os7WaitAnyEPNS0_13MultiWaitTypeE"]
+        pub fn WaitAny(
+            multi_wait: *mut MultiWaitType
+        ) -> *mut MultiWaitHolderType;
+
+        #[link_name = "\u{1}_ZN2nn2os10TryWaitAnyEPNS0_13MultiWaitTypeE"]
+        pub fn TryWaitAny(
+            multi_wait: *mut MultiWaitType
+        ) -> *mut MultiWaitHolderType;
+
+        #[link_name = "\u{1}_ZN2nn2os12TimedWaitAnyEPNS0_13MultiWaitTypeENS_8TimeSpanE"]
+        pub fn TimedWaitAny(
+            multi_wait: *mut MultiWaitType,
+            timeout: TimeSpan
+        ) -> *mut MultiWaitHolderType;
+
+        #[link_name = "\u{1}_ZN2nn2os26SetMultiWaitHolderUserDataEPNS0_19MultiWaitHolderTypeEm"]
+        pub fn SetMultiWaitHolderUserData(
+            holder: *mut MultiWaitHolderType,
+            user_data: usize
+        );
+
+        #[link_name = "\u{1}_ZN2nn2os26GetMultiWaitHolderUserDataEPKNS0_19MultiWaitHolderTypeE"]
+        pub fn GetMultiWaitHolderUserData(
+            holder: *const MultiWaitHolderType
+        ) -> usize;
+
         // Other OS stuff
 
         #[link_name = "\u{1}_ZN2nn2os17SetMemoryHeapSizeEm"]
@@ -124,6 +556,10 @@ pub fn get_host_args() -> Vec<String> {
     }
 }
 
+// Heap sizes and memory block sizes have to be multiples of these
+pub const MEMORY_HEAP_UNIT_SIZE: usize = 0x200000;
+pub const MEMORY_BLOCK_UNIT_SIZE: usize = 0x200000;
+
 #[dev_inline]
 pub fn set_heap_size(size: usize) -> Result<(), NxResult> {
     unsafe {
@@ -149,7 +585,134 @@ struct ThreadType {
     _x0: [u8; 0x1C0]
 }
 
-pub struct Thread(*mut ThreadType);
+const MUTEX_STATE_UNINITIALIZED: u8 = 0;
+const MUTEX_STATE_INITIALIZED: u8 = 1;
+
+// Same layout as the SDK's NN_OS_MUTEX_INITIALIZER, so it can be built in a const and used without InitializeMutex
+#[repr(C)]
+struct MutexType {
+    state: u8,
+    is_recursive: bool,
+    lock_level: i32,
+    nest_count: i32,
+    owner_thread: *mut ThreadType,
+    _x18: [u32; 2]
+}
+
+const _: () = assert!(core::mem::size_of::<MutexType>() == 0x20);
+
+impl MutexType {
+    const fn new(is_recursive: bool) -> Self {
+        Self {
+            state: MUTEX_STATE_INITIALIZED,
+            is_recursive,
+            lock_level: 0,
+            nest_count: 0,
+            owner_thread: core::ptr::null_mut(),
+            _x18: [0; 2]
+        }
+    }
+}
+
+const CONDITION_VARIABLE_STATE_UNINITIALIZED: u8 = 0;
+const CONDITION_VARIABLE_STATE_INITIALIZED: u8 = 1;
+
+// nn::os::ConditionVariableStatus
+const CONDITION_VARIABLE_STATUS_TIMEOUT: i32 = 0;
+const CONDITION_VARIABLE_STATUS_NO_TIMEOUT: i32 = 1;
+
+// Same layout as NN_OS_CONDITION_VARIABLE_INITIALIZER
+#[repr(C)]
+struct ConditionVariableType {
+    state: u8,
+    _x4: u32
+}
+
+const _: () = assert!(core::mem::size_of::<ConditionVariableType>() == 0x8);
+
+impl ConditionVariableType {
+    const fn new() -> Self {
+        Self {
+            state: CONDITION_VARIABLE_STATE_INITIALIZED,
+            _x4: 0
+        }
+    }
+}
+
+// A kernel handle, services give these out for the system events they signal
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NativeHandle(pub u32);
+
+impl NativeHandle {
+    pub const INVALID: Self = Self(0);
+}
+
+const EVENT_STATE_UNINITIALIZED: u8 = 0;
+const EVENT_STATE_INITIALIZED: u8 = 1;
+
+#[repr(C)]
+struct EventType {
+    // Head of the list of MultiWaitHolders linked to the event, points into the event itself so it can't be moved
+    multi_wait_holders: [usize; 2],
+    signaled: bool,
+    initially_signaled: bool,
+    clear_mode: u8,
+    state: u8,
+    broadcast_counter_low: u32,
+    broadcast_counter_high: u32,
+    _x1c: [u32; 3]
+}
+
+const _: () = assert!(core::mem::size_of::<EventType>() == 0x28);
+
+// Light events can't be linked to a MultiWait, in exchange they're only a few atomics
+#[repr(C)]
+struct LightEventType {
+    _x0: [u32; 3]
+}
+
+#[repr(C)]
+struct SystemEventType {
+    _x0: [u64; 6]
+}
+
+#[repr(C)]
+struct SemaphoreType {
+    _x0: [u64; 5]
+}
+
+// Rounded up, the SDK only needs 0x48 bytes
+#[repr(C)]
+struct MessageQueueType {
+    _x0: [u64; 10]
+}
+
+#[repr(C)]
+struct MultiWaitType {
+    _x0: [u64; 0x40]
+}
+
+#[repr(C)]
+struct MultiWaitHolderType {
+    _x0: [u64; 8]
+}
+
+// A thread created by Thread::new, destroyed and freed when dropped
+pub struct Thread {
+    thread: core::ptr::NonNull<ThreadType>,
+    // Heap copy of the last long name given to set_name, kept until it's replaced or the thread is dropped.
+    // ThreadHandle::set_name can point the ThreadType back at its own buffer, so this is the only owner.
+    name: *mut c_char
+}
+
+// A borrowed ThreadType, owned either by a Thread or by the SDK for threads it created itself.
+// It is never Sync, so a &ThreadHandle can't be moved off the thread that obtained it.
+#[repr(transparent)]
+pub struct ThreadHandle(core::cell::UnsafeCell<ThreadType>);
+
+// Thread only holds the ThreadType and its name, which the SDK lets any thread start, wait on or destroy
+unsafe impl Send for Thread {}
 
 impl Thread {
     pub const PRIORITY_MAX: i32 = 0;
@@ -158,21 +721,15 @@ impl Thread {
 
     pub const MAX_NAME_LEN: usize = 32;
 
-    fn free_name(&mut self) {
-        unsafe {
-            let range = (self.0 as usize)..(self.0 as usize + core::mem::size_of::<Self>());
-            let name_ptr = os_impl::GetThreadNamePointer(self.0);
-            if !range.contains(&(name_ptr as usize)) {
-                os_impl::SetThreadNamePointer(self.0, core::ptr::null());
-                free(name_ptr as _);
-            }
-        }
+    fn as_ptr(&self) -> *mut ThreadType {
+        self.thread.as_ptr()
     }
 
+    // Lives as long as the current thread, and the reference can't be sent to another one
     #[dev_inline]
-    pub fn current() -> Self {
+    pub fn current() -> &'static ThreadHandle {
         unsafe {
-            Self(os_impl::GetCurrentThread())
+            ThreadHandle::from_ptr(os_impl::GetCurrentThread())
         }
     }
 
@@ -200,98 +757,156 @@ impl Thread {
     #[dev_inline]
     pub fn new(main: ThreadFn, arg: *mut c_void, stack: *mut c_void, stack_size: usize, priority: i32) -> Result<Self, NxResult> {
         unsafe {
-            let thread = calloc(1, core::mem::size_of::<ThreadType>()) as *mut ThreadType;
-            let result = os_impl::CreateThread(thread, main, arg, stack, stack_size, priority);
-            if result.is_success() {
-                Ok(Self(thread))
-            } else {
-                free(thread as _);
-                Err(result)
-            }
+            Self::create(|thread| os_impl::CreateThread(thread, main, arg, stack, stack_size, priority))
         }
     }
 
     #[dev_inline]
     pub fn new_on_core(main: ThreadFn, arg: *mut c_void, stack: *mut c_void, stack_size: usize, priority: i32, core: i32) -> Result<Self, NxResult> {
         unsafe {
-            let thread = calloc(1, core::mem::size_of::<ThreadType>()) as *mut ThreadType;
-            let result = os_impl::CreateThreadOnCore(thread, main, arg, stack, stack_size, priority, core);
-            if result.is_success() {
-                Ok(Self(thread))
-            } else {
-                free(thread as _);
-                Err(result)
-            }
+            Self::create(|thread| os_impl::CreateThreadOnCore(thread, main, arg, stack, stack_size, priority, core))
         }
     }
 
-    #[dev_inline]
-    pub fn destroy(mut self) {
-        unsafe {
-            os_impl::DestroyThread(self.0);
-            self.free_name();
-            free(self.0 as _);
+    unsafe fn create<F: FnOnce(*mut ThreadType) -> NxResult>(create: F) -> Result<Self, NxResult> {
+        let thread = match core::ptr::NonNull::new(calloc(1, core::mem::size_of::<ThreadType>()) as *mut ThreadType) {
+            Some(thread) => thread,
+            None => return Err(result::OUT_OF_MEMORY)
+        };
+        let result = create(thread.as_ptr());
+        if result.is_success() {
+            Ok(Self {
+                thread,
+                name: core::ptr::null_mut()
+            })
+        } else {
+            free(thread.as_ptr() as _);
+            Err(result)
         }
     }
 
+    // Same as dropping the Thread, which waits for it to exit if it was started
+    #[dev_inline]
+    pub fn destroy(self) {
+        drop(self)
+    }
+
     #[dev_inline]
     pub fn start(&mut self) {
         unsafe {
-            os_impl::StartThread(self.0)
+            os_impl::StartThread(self.as_ptr())
         }
     }
 
     #[dev_inline]
     pub fn wait(&mut self) {
         unsafe {
-            os_impl::WaitThread(self.0)
+            os_impl::WaitThread(self.as_ptr())
+        }
+    }
+
+    // Unlike ThreadHandle::set_name, names of MAX_NAME_LEN bytes or more are kept whole on the heap
+    #[dev_inline]
+    pub fn set_name<S: AsRef<str>>(&mut self, name: S) {
+        unsafe {
+            let name = name.as_ref();
+            if name.len() < Self::MAX_NAME_LEN {
+                os_impl::SetThreadName(self.as_ptr(), c_str!(name));
+                free(self.name as _);
+                self.name = core::ptr::null_mut();
+            } else {
+                let name_ptr = calloc(1, name.len() + 1) as *mut c_char;
+                if name_ptr.is_null() {
+                    // Out of memory, fall back to a truncated copy like ThreadHandle::set_name
+                    os_impl::SetThreadName(self.as_ptr(), c_str!(name));
+                } else {
+                    memcpy(name_ptr as _, name.as_ptr() as _, name.len());
+                    os_impl::SetThreadNamePointer(self.as_ptr(), name_ptr);
+                }
+                // The ThreadType no longer points at the old buffer either way
+                free(self.name as _);
+                self.name = name_ptr;
+            }
+        }
+    }
+}
+
+impl core::ops::Deref for Thread {
+    type Target = ThreadHandle;
+
+    fn deref(&self) -> &ThreadHandle {
+        unsafe {
+            ThreadHandle::from_ptr(self.as_ptr())
         }
     }
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::DestroyThread(self.as_ptr());
+            free(self.name as _);
+            free(self.as_ptr() as _);
+        }
+    }
+}
+
+impl ThreadHandle {
+    unsafe fn from_ptr<'a>(thread: *mut ThreadType) -> &'a Self {
+        &*(thread as *const Self)
+    }
+
+    fn as_ptr(&self) -> *mut ThreadType {
+        self.0.get()
+    }
 
     #[dev_inline]
-    pub fn set_priority(&mut self, priority: i32) -> i32 {
+    pub fn set_priority(&self, priority: i32) -> i32 {
         unsafe {
-            os_impl::ChangeThreadPriority(self.0, priority)
+            os_impl::ChangeThreadPriority(self.as_ptr(), priority)
         }
     }
 
     #[dev_inline]
     pub fn get_original_priority(&self) -> i32 {
         unsafe {
-            os_impl::GetThreadPriority(self.0)
+            os_impl::GetThreadPriority(self.as_ptr())
         }
     }
 
     #[dev_inline]
     pub fn get_current_priority(&self) -> i32 {
         unsafe {
-            os_impl::GetThreadCurrentPriority(self.0)
+            os_impl::GetThreadCurrentPriority(self.as_ptr())
         }
     }
 
+    // Copied into the ThreadType's buffer, names of Thread::MAX_NAME_LEN bytes or more are truncated
     #[dev_inline]
-    pub fn set_name<S: AsRef<str>>(&mut self, name: S) {
+    pub fn set_name<S: AsRef<str>>(&self, name: S) {
         unsafe {
-            self.free_name();
             let name = name.as_ref();
-            if name.len() < Self::MAX_NAME_LEN {
-                os_impl::SetThreadName(self.0, c_str!(name));
-            } else {
-                let name_ptr = calloc(1, name.len() + 1);
-                memcpy(name_ptr, name.as_ptr() as _, name.len());
-                os_impl::SetThreadNamePointer(self.0, name_ptr as _);
-            }
+            os_impl::SetThreadName(self.as_ptr(), c_str!(name));
         }
     }
 
     #[dev_inline]
     pub fn get_name(&self) -> String {
         unsafe {
-            let name_ptr = os_impl::GetThreadNamePointer(self.0);
+            let name_ptr = os_impl::GetThreadNamePointer(self.as_ptr());
             match super::from_c_str(name_ptr) {
                 Ok(v) => v,
                 Err(_) => String::from("")
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Two handles are equal when they refer to the same thread
+impl PartialEq for ThreadHandle {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self, other)
+    }
+}
+
+impl Eq for ThreadHandle {}
\ No newline at end of file
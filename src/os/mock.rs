@@ -0,0 +1,1961 @@
+// Host implementation of the nn::os symbols, enabled with the mock_os feature.
+// Threads run on std threads with their priority and core kept as bookkeeping only, the memory heap
+// is a single host reservation carved up with the SDK's size and alignment rules. Synchronization objects
+// keep their state in the SDK's structs, guarded by one host mutex. Multi waits poll their holders under that same lock.
+#![allow(non_snake_case)]
+
+use std::alloc::{alloc, Layout};
+use std::boxed::Box;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::string::String;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use std::vec::Vec;
+
+use libc::*;
+use super::super::{Result, TimeSpan};
+use super::{result, ConditionVariableType, EventType, LightEventType, MutexType, NativeHandle, SystemEventType, Thread, ThreadFn, ThreadType, MEMORY_BLOCK_UNIT_SIZE, MEMORY_HEAP_UNIT_SIZE};
+use super::{MessageQueueType, MultiWaitHolderType, MultiWaitType, SemaphoreType};
+use super::{MUTEX_STATE_INITIALIZED, MUTEX_STATE_UNINITIALIZED};
+use super::{CONDITION_VARIABLE_STATE_INITIALIZED, CONDITION_VARIABLE_STATE_UNINITIALIZED};
+use super::{CONDITION_VARIABLE_STATUS_NO_TIMEOUT, CONDITION_VARIABLE_STATUS_TIMEOUT};
+use super::{EVENT_STATE_INITIALIZED, EVENT_STATE_UNINITIALIZED};
+use super::sync::{EventClearMode, MessageQueueWaitType};
+use super::tls::{TlsDestructor, TlsSlot};
+
+const CORE_COUNT: i32 = 4;
+// Passed as the core by CreateThread, the thread runs on the process' default core
+const DEFAULT_CORE: i32 = -2;
+const STACK_ALIGNMENT: usize = 0x1000;
+
+// Address space reserved for the heap, SetMemoryHeapSize fails with OutOfMemory past this
+const MEMORY_HEAP_RESERVE_SIZE: usize = 0x4000_0000;
+
+// Mirrors where the SDK keeps the name in its ThreadType, the rest of the 0x1C0 bytes is unused here
+#[repr(C)]
+struct MockThreadType {
+    state: *mut ThreadState,
+    _x8: [u8; 0x180],
+    name_buffer: [u8; Thread::MAX_NAME_LEN],
+    name_pointer: *const c_char,
+    _x1b0: [u8; 0x10]
+}
+
+const _: () = assert!(core::mem::size_of::<MockThreadType>() == core::mem::size_of::<ThreadType>());
+
+struct ThreadState {
+    entry: Option<ThreadFn>,
+    arg: usize,
+    stack_size: usize,
+    priority: AtomicI32,
+    core: i32,
+    started: AtomicBool,
+    // Set once the entrypoint has returned, for multi waits on the thread
+    exited: AtomicBool,
+    join_handle: Mutex<Option<JoinHandle<()>>>
+}
+
+// Raw pointers aren't Send, the SDK leaves it to the caller to keep the ThreadType alive
+#[derive(Copy, Clone)]
+struct ThreadPtr(*mut MockThreadType);
+
+unsafe impl Send for ThreadPtr {}
+
+std::thread_local! {
+    static CURRENT_THREAD: Cell<*mut MockThreadType> = const { Cell::new(core::ptr::null_mut()) };
+}
+
+fn check_priority(priority: i32) {
+    if !(Thread::PRIORITY_MAX..=Thread::PRIORITY_MIN).contains(&priority) {
+        panic!("nn::os: thread priority {} is out of range {}..={}", priority, Thread::PRIORITY_MAX, Thread::PRIORITY_MIN);
+    }
+}
+
+unsafe fn mock_thread<'a>(thread: *const ThreadType) -> &'a mut MockThreadType {
+    if thread.is_null() {
+        panic!("nn::os: null ThreadType");
+    }
+    &mut *(thread as *mut MockThreadType)
+}
+
+unsafe fn thread_state<'a>(thread: *const ThreadType) -> &'a ThreadState {
+    let state = mock_thread(thread).state;
+    if state.is_null() {
+        panic!("nn::os: ThreadType {:p} is not initialized", thread);
+    }
+    &*state
+}
+
+unsafe fn set_name(thread: &mut MockThreadType, name: &[u8]) {
+    let len = core::cmp::min(name.len(), Thread::MAX_NAME_LEN - 1);
+    thread.name_buffer = [0; Thread::MAX_NAME_LEN];
+    thread.name_buffer[..len].copy_from_slice(&name[..len]);
+    thread.name_pointer = thread.name_buffer.as_ptr() as *const c_char;
+}
+
+unsafe fn initialize_thread(thread: *mut MockThreadType, state: ThreadState) {
+    core::ptr::write_bytes(thread, 0, 1);
+    (*thread).state = Box::into_raw(Box::new(state));
+    let name = std::format!("Thread_0x{:016x}", thread as usize);
+    set_name(&mut *thread, name.as_bytes());
+}
+
+// Threads that weren't created through CreateThread, such as the main thread, get a ThreadType the first time they ask for one
+fn current_thread() -> *mut MockThreadType {
+    CURRENT_THREAD.with(|current| {
+        if current.get().is_null() {
+            unsafe {
+                let thread = Box::into_raw(Box::new(core::mem::zeroed::<MockThreadType>()));
+                initialize_thread(thread, ThreadState {
+                    entry: None,
+                    arg: 0,
+                    stack_size: 0,
+                    priority: AtomicI32::new(Thread::PRIORITY_DEFAULT),
+                    core: DEFAULT_CORE,
+                    started: AtomicBool::new(true),
+                    exited: AtomicBool::new(false),
+                    join_handle: Mutex::new(None)
+                });
+                match std::thread::current().name() {
+                    Some("main") => set_name(&mut *thread, b"MainThread"),
+                    Some(name) => set_name(&mut *thread, name.as_bytes()),
+                    None => {}
+                }
+                current.set(thread);
+            }
+        }
+        current.get()
+    })
+}
+
+pub unsafe fn GetHostArgc() -> i32 {
+    std::env::args_os().count() as i32
+}
+
+pub unsafe fn GetHostArgv() -> *const *const c_char {
+    static ARGV: OnceLock<usize> = OnceLock::new();
+    *ARGV.get_or_init(|| {
+        let argv: Vec<*const c_char> = std::env::args()
+            .map(|arg| CString::new(arg).unwrap_or_default().into_raw() as *const c_char)
+            .collect();
+        Box::leak(argv.into_boxed_slice()).as_ptr() as usize
+    }) as *const *const c_char
+}
+
+pub unsafe fn CreateThread(thread: *mut ThreadType, entrypoint: ThreadFn, arg: *mut c_void, stack: *mut c_void, stack_size: usize, priority: i32) -> Result {
+    CreateThreadOnCore(thread, entrypoint, arg, stack, stack_size, priority, DEFAULT_CORE)
+}
+
+pub unsafe fn CreateThreadOnCore(thread: *mut ThreadType, entrypoint: ThreadFn, arg: *mut c_void, stack: *mut c_void, stack_size: usize, priority: i32, core: i32) -> Result {
+    if thread.is_null() {
+        panic!("nn::os::CreateThread: null ThreadType");
+    }
+    if stack.is_null() || !(stack as usize).is_multiple_of(STACK_ALIGNMENT) {
+        panic!("nn::os::CreateThread: stack {:p} is not aligned to {:#x}", stack, STACK_ALIGNMENT);
+    }
+    if stack_size == 0 || !stack_size.is_multiple_of(STACK_ALIGNMENT) {
+        panic!("nn::os::CreateThread: stack size {:#x} is not a multiple of {:#x}", stack_size, STACK_ALIGNMENT);
+    }
+    check_priority(priority);
+    if core != DEFAULT_CORE && !(0..CORE_COUNT).contains(&core) {
+        panic!("nn::os::CreateThread: core {} is out of range 0..{}", core, CORE_COUNT);
+    }
+
+    initialize_thread(thread as *mut MockThreadType, ThreadState {
+        entry: Some(entrypoint),
+        arg: arg as usize,
+        stack_size,
+        priority: AtomicI32::new(priority),
+        core,
+        started: AtomicBool::new(false),
+        exited: AtomicBool::new(false),
+        join_handle: Mutex::new(None)
+    });
+    Result::SUCCESS
+}
+
+pub unsafe fn DestroyThread(thread: *mut ThreadType) {
+    // The SDK waits for a started thread to exit before tearing it down
+    WaitThread(thread);
+    let thread = mock_thread(thread);
+    drop(Box::from_raw(thread.state));
+    thread.state = core::ptr::null_mut();
+    thread.name_pointer = core::ptr::null();
+}
+
+pub unsafe fn StartThread(thread: *mut ThreadType) {
+    let state = thread_state(thread);
+    if state.started.swap(true, Ordering::AcqRel) {
+        panic!("nn::os::StartThread: thread {:p} was already started", thread);
+    }
+
+    let entry = match state.entry {
+        Some(entry) => entry,
+        None => panic!("nn::os::StartThread: thread {:p} has no entrypoint", thread)
+    };
+    let arg = state.arg;
+    let ptr = ThreadPtr(thread as *mut MockThreadType);
+    let name = super::super::from_c_str(GetThreadNamePointer(thread)).unwrap_or_else(|_| String::new());
+    let handle = std::thread::Builder::new()
+        .name(name)
+        // The caller's stack is only validated, std needs to own the stack it runs on
+        .stack_size(core::cmp::max(state.stack_size, 0x10000))
+        .spawn(move || {
+            let ptr = ptr;
+            CURRENT_THREAD.with(|current| current.set(ptr.0));
+            entry(arg as *mut c_void);
+            run_tls_destructors();
+            let _guard = sync_lock();
+            thread_state(ptr.0 as *const ThreadType).exited.store(true, Ordering::Release);
+            sync_wake();
+        })
+        .expect("nn::os::StartThread: failed to spawn host thread");
+    *state.join_handle.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+}
+
+pub unsafe fn WaitThread(thread: *mut ThreadType) {
+    let state = thread_state(thread);
+    if !state.started.load(Ordering::Acquire) {
+        return;
+    }
+    if thread as *mut MockThreadType == current_thread() {
+        panic!("nn::os::WaitThread: thread {:p} can't wait on itself", thread);
+    }
+    // Waiting on a thread that already exited returns immediately, same as the SDK
+    let handle = state.join_handle.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+}
+
+pub unsafe fn SleepThread(time: TimeSpan) {
+    std::thread::sleep(core::time::Duration::from_nanos(time.as_nanos()));
+}
+
+pub unsafe fn YieldThread() {
+    std::thread::yield_now();
+}
+
+pub unsafe fn GetCurrentThread() -> *mut ThreadType {
+    current_thread() as *mut ThreadType
+}
+
+// Returns the previous priority
+pub unsafe fn ChangeThreadPriority(thread: *mut ThreadType, priority: i32) -> i32 {
+    check_priority(priority);
+    thread_state(thread).priority.swap(priority, Ordering::AcqRel)
+}
+
+pub unsafe fn GetThreadPriority(thread: *const ThreadType) -> i32 {
+    thread_state(thread).priority.load(Ordering::Acquire)
+}
+
+// There is no priority inheritance on the host, so the current priority is always the original one
+pub unsafe fn GetThreadCurrentPriority(thread: *const ThreadType) -> i32 {
+    GetThreadPriority(thread)
+}
+
+pub unsafe fn SetThreadName(thread: *mut ThreadType, name: *const c_char) {
+    let name = if name.is_null() {
+        &[][..]
+    } else {
+        core::slice::from_raw_parts(name.cast::<u8>(), strlen(name))
+    };
+    set_name(mock_thread(thread), name);
+}
+
+pub unsafe fn SetThreadNamePointer(thread: *mut ThreadType, name: *const c_char) {
+    let thread = mock_thread(thread);
+    // A null name goes back to the thread's own buffer, like the SDK's default name
+    if name.is_null() {
+        thread.name_pointer = thread.name_buffer.as_ptr() as *const c_char;
+    } else {
+        thread.name_pointer = name;
+    }
+}
+
+pub unsafe fn GetThreadNamePointer(thread: *const ThreadType) -> *const c_char {
+    mock_thread(thread).name_pointer
+}
+
+pub unsafe fn GetCurrentCoreNumber() -> i32 {
+    match thread_state(current_thread() as *const ThreadType).core {
+        DEFAULT_CORE => 0,
+        core => core
+    }
+}
+
+// Thread local storage
+
+// Slots available to applications, the SDK keeps a few more for itself
+const TLS_SLOT_COUNT: usize = 16;
+
+#[derive(Copy, Clone)]
+struct TlsSlotInfo {
+    allocated: bool,
+    // Bumped every time the slot is allocated, so values left over from its previous owner read as 0
+    generation: u64,
+    destructor: Option<TlsDestructor>
+}
+
+static TLS_SLOTS: Mutex<[TlsSlotInfo; TLS_SLOT_COUNT]> = Mutex::new([TlsSlotInfo {
+    allocated: false,
+    generation: 0,
+    destructor: None
+}; TLS_SLOT_COUNT]);
+
+std::thread_local! {
+    // Generation of the slot each value was set under, and the value
+    static TLS_VALUES: Cell<[(u64, usize); TLS_SLOT_COUNT]> = const { Cell::new([(0, 0); TLS_SLOT_COUNT]) };
+}
+
+fn tls_slot_info(slot: TlsSlot, function: &str) -> (usize, TlsSlotInfo) {
+    let index = slot.as_raw() as usize;
+    let slots = TLS_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    match slots.get(index) {
+        Some(info) if info.allocated => (index, *info),
+        _ => panic!("nn::os::{}: TLS slot {} is not allocated", function, index)
+    }
+}
+
+// Like the SDK, destructors only run for threads created with CreateThread and only for values that aren't 0
+fn run_tls_destructors() {
+    let slots = *TLS_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    for (index, info) in slots.iter().enumerate() {
+        let destructor = match info.destructor {
+            Some(destructor) if info.allocated => destructor,
+            _ => continue
+        };
+        let value = TLS_VALUES.with(|values| {
+            let mut current = values.get();
+            let (generation, value) = current[index];
+            current[index] = (0, 0);
+            values.set(current);
+            if generation == info.generation { value } else { 0 }
+        });
+        if value != 0 {
+            destructor(value);
+        }
+    }
+}
+
+pub unsafe fn AllocateTlsSlot(slot: *mut TlsSlot, destructor: Option<TlsDestructor>) -> Result {
+    let mut slots = TLS_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    match slots.iter().position(|info| !info.allocated) {
+        Some(index) => {
+            let info = &mut slots[index];
+            info.allocated = true;
+            info.generation += 1;
+            info.destructor = destructor;
+            *slot = TlsSlot::from_raw(index as u32);
+            Result::SUCCESS
+        },
+        None => result::OUT_OF_RESOURCE
+    }
+}
+
+pub unsafe fn FreeTlsSlot(slot: TlsSlot) {
+    let (index, _) = tls_slot_info(slot, "FreeTlsSlot");
+    let mut slots = TLS_SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+    slots[index].allocated = false;
+    slots[index].destructor = None;
+}
+
+pub unsafe fn GetTlsValue(slot: TlsSlot) -> usize {
+    let (index, info) = tls_slot_info(slot, "GetTlsValue");
+    TLS_VALUES.with(|values| {
+        let (generation, value) = values.get()[index];
+        if generation == info.generation { value } else { 0 }
+    })
+}
+
+pub unsafe fn SetTlsValue(slot: TlsSlot, value: usize) {
+    let (index, info) = tls_slot_info(slot, "SetTlsValue");
+    TLS_VALUES.with(|values| {
+        let mut current = values.get();
+        current[index] = (info.generation, value);
+        values.set(current);
+    });
+}
+
+// Synchronization
+
+// Every synchronization object shares one host lock. Blocked threads wait on SYNC_CONDVAR and
+// check their object again whenever anything is released, which is slow but can't miss a wakeup.
+// The lock also guards the kernel events behind system events and the handles pointing to them.
+static SYNC_LOCK: Mutex<Kernel> = Mutex::new(Kernel {
+    next_handle: 0xE000_0001,
+    handles: BTreeMap::new(),
+    next_event: 0,
+    events: BTreeMap::new()
+});
+static SYNC_CONDVAR: Condvar = Condvar::new();
+
+struct Kernel {
+    next_handle: u32,
+    handles: BTreeMap<u32, KernelHandle>,
+    next_event: usize,
+    events: BTreeMap<usize, KernelEvent>
+}
+
+#[derive(Copy, Clone)]
+struct KernelHandle {
+    event: usize,
+    writable: bool
+}
+
+struct KernelEvent {
+    signaled: bool,
+    // Closed along with its last handle
+    handle_count: usize
+}
+
+type SyncGuard = MutexGuard<'static, Kernel>;
+
+fn sync_lock() -> SyncGuard {
+    SYNC_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn sync_wait(guard: SyncGuard) -> SyncGuard {
+    SYNC_CONDVAR.wait(guard).unwrap_or_else(|e| e.into_inner())
+}
+
+// Like sync_wait, but gives up at the deadline. The bool is false once it has passed.
+fn sync_wait_until(guard: SyncGuard, deadline: Option<Instant>) -> (SyncGuard, bool) {
+    match deadline {
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                return (guard, false);
+            }
+            (SYNC_CONDVAR.wait_timeout(guard, deadline - now).unwrap_or_else(|e| e.into_inner()).0, true)
+        },
+        None => (sync_wait(guard), true)
+    }
+}
+
+fn deadline_after(timeout: TimeSpan) -> Instant {
+    Instant::now() + core::time::Duration::from_nanos(timeout.as_nanos())
+}
+
+// Blocks until ready returns true, or returns false once the deadline passes
+fn sync_wait_for<F: FnMut(&mut Kernel) -> bool>(deadline: Option<Instant>, mut ready: F) -> bool {
+    let mut guard = sync_lock();
+    loop {
+        if ready(&mut guard) {
+            return true;
+        }
+        let (next, waiting) = sync_wait_until(guard, deadline);
+        if !waiting {
+            return false;
+        }
+        guard = next;
+    }
+}
+
+fn sync_wake() {
+    SYNC_CONDVAR.notify_all();
+}
+
+unsafe fn initialized_mutex<'a>(mutex: *mut MutexType, function: &str) -> &'a mut MutexType {
+    if mutex.is_null() {
+        panic!("nn::os::{}: null MutexType", function);
+    }
+    let mutex = &mut *mutex;
+    if mutex.state != MUTEX_STATE_INITIALIZED {
+        panic!("nn::os::{}: mutex {:p} is not initialized", function, mutex);
+    }
+    mutex
+}
+
+// Takes the mutex if it's free or already held recursively by the current thread
+unsafe fn try_acquire_mutex(mutex: &mut MutexType, function: &str) -> bool {
+    let current = GetCurrentThread();
+    if mutex.owner_thread.is_null() {
+        mutex.owner_thread = current;
+        mutex.nest_count = 1;
+        true
+    } else if mutex.owner_thread == current {
+        if !mutex.is_recursive {
+            panic!("nn::os::{}: non-recursive mutex {:p} is already locked by the current thread", function, mutex);
+        }
+        mutex.nest_count += 1;
+        true
+    } else {
+        false
+    }
+}
+
+pub unsafe fn InitializeMutex(mutex: *mut MutexType, is_recursive: bool, lock_level: i32) {
+    if mutex.is_null() {
+        panic!("nn::os::InitializeMutex: null MutexType");
+    }
+    // Lock levels are only checked in debug builds of the SDK, they're validated but not enforced here.
+    // 0 means no level, the same as a mutex built by MutexType::new.
+    if !(0..=31).contains(&lock_level) {
+        panic!("nn::os::InitializeMutex: lock level {} is out of range 0..=31", lock_level);
+    }
+    let _guard = sync_lock();
+    let mut new = MutexType::new(is_recursive);
+    new.lock_level = lock_level;
+    core::ptr::write(mutex, new);
+}
+
+pub unsafe fn FinalizeMutex(mutex: *mut MutexType) {
+    let _guard = sync_lock();
+    let mutex = initialized_mutex(mutex, "FinalizeMutex");
+    if !mutex.owner_thread.is_null() {
+        panic!("nn::os::FinalizeMutex: mutex {:p} is still locked", mutex);
+    }
+    mutex.state = MUTEX_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn LockMutex(mutex: *mut MutexType) {
+    let mut guard = sync_lock();
+    // Other threads change the MutexType while this one waits, so it's looked up again every time
+    while !try_acquire_mutex(initialized_mutex(mutex, "LockMutex"), "LockMutex") {
+        guard = sync_wait(guard);
+    }
+}
+
+pub unsafe fn TryLockMutex(mutex: *mut MutexType) -> bool {
+    let _guard = sync_lock();
+    try_acquire_mutex(initialized_mutex(mutex, "TryLockMutex"), "TryLockMutex")
+}
+
+pub unsafe fn UnlockMutex(mutex: *mut MutexType) {
+    let _guard = sync_lock();
+    let mutex = initialized_mutex(mutex, "UnlockMutex");
+    if mutex.owner_thread != GetCurrentThread() {
+        panic!("nn::os::UnlockMutex: mutex {:p} is not locked by the current thread", mutex);
+    }
+    mutex.nest_count -= 1;
+    if mutex.nest_count == 0 {
+        mutex.owner_thread = core::ptr::null_mut();
+        sync_wake();
+    }
+}
+
+pub unsafe fn IsMutexLockedByCurrentThread(mutex: *const MutexType) -> bool {
+    let _guard = sync_lock();
+    initialized_mutex(mutex as *mut MutexType, "IsMutexLockedByCurrentThread").owner_thread == GetCurrentThread()
+}
+
+// Threads blocked on each condition variable, keyed by address. Entries only exist while someone is waiting,
+// so a ConditionVariableType nobody waits on can be moved like the SDK allows.
+struct CondvarWaiters {
+    waiting: usize,
+    // Wakeups handed out by Signal/Broadcast that no waiter has taken yet
+    signals: usize
+}
+
+static CONDVAR_WAITERS: Mutex<BTreeMap<usize, CondvarWaiters>> = Mutex::new(BTreeMap::new());
+
+unsafe fn initialized_condvar<'a>(condvar: *mut ConditionVariableType, function: &str) -> &'a mut ConditionVariableType {
+    if condvar.is_null() {
+        panic!("nn::os::{}: null ConditionVariableType", function);
+    }
+    let condvar = &mut *condvar;
+    if condvar.state != CONDITION_VARIABLE_STATE_INITIALIZED {
+        panic!("nn::os::{}: condition variable {:p} is not initialized", function, condvar);
+    }
+    condvar
+}
+
+fn condvar_waiters<R, F: FnOnce(&mut BTreeMap<usize, CondvarWaiters>) -> R>(f: F) -> R {
+    f(&mut CONDVAR_WAITERS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+// Releases the mutex, blocks until signaled or the deadline passes and takes the mutex back. Returns whether it was signaled.
+unsafe fn wait_condvar(condvar: *mut ConditionVariableType, mutex: *mut MutexType, deadline: Option<Instant>, function: &str) -> bool {
+    let mut guard = sync_lock();
+    let key = initialized_condvar(condvar, function) as *mut ConditionVariableType as usize;
+    {
+        let mutex = initialized_mutex(mutex, function);
+        if mutex.owner_thread != GetCurrentThread() {
+            panic!("nn::os::{}: mutex {:p} is not locked by the current thread", function, mutex);
+        }
+        if mutex.nest_count != 1 {
+            panic!("nn::os::{}: recursive mutex {:p} is locked {} times", function, mutex, mutex.nest_count);
+        }
+        mutex.owner_thread = core::ptr::null_mut();
+        mutex.nest_count = 0;
+    }
+    sync_wake();
+
+    condvar_waiters(|waiters| waiters.entry(key).or_insert(CondvarWaiters { waiting: 0, signals: 0 }).waiting += 1);
+    let signaled = loop {
+        let taken = condvar_waiters(|waiters| {
+            let entry = waiters.get_mut(&key).unwrap();
+            let taken = entry.signals != 0;
+            if taken {
+                entry.signals -= 1;
+            }
+            taken
+        });
+        if taken {
+            break true;
+        }
+        let (next, waiting) = sync_wait_until(guard, deadline);
+        guard = next;
+        if !waiting {
+            break false;
+        }
+    };
+    condvar_waiters(|waiters| {
+        let entry = waiters.get_mut(&key).unwrap();
+        entry.waiting -= 1;
+        entry.signals = core::cmp::min(entry.signals, entry.waiting);
+        if entry.waiting == 0 {
+            waiters.remove(&key);
+        }
+    });
+
+    while !try_acquire_mutex(initialized_mutex(mutex, function), function) {
+        guard = sync_wait(guard);
+    }
+    signaled
+}
+
+pub unsafe fn InitializeConditionVariable(condvar: *mut ConditionVariableType) {
+    if condvar.is_null() {
+        panic!("nn::os::InitializeConditionVariable: null ConditionVariableType");
+    }
+    let _guard = sync_lock();
+    core::ptr::write(condvar, ConditionVariableType::new());
+}
+
+pub unsafe fn FinalizeConditionVariable(condvar: *mut ConditionVariableType) {
+    let _guard = sync_lock();
+    let condvar = initialized_condvar(condvar, "FinalizeConditionVariable");
+    if condvar_waiters(|waiters| waiters.contains_key(&(condvar as *mut ConditionVariableType as usize))) {
+        panic!("nn::os::FinalizeConditionVariable: threads are still waiting on {:p}", condvar);
+    }
+    condvar.state = CONDITION_VARIABLE_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn SignalConditionVariable(condvar: *mut ConditionVariableType) {
+    let _guard = sync_lock();
+    let key = initialized_condvar(condvar, "SignalConditionVariable") as *mut ConditionVariableType as usize;
+    condvar_waiters(|waiters| {
+        if let Some(entry) = waiters.get_mut(&key) {
+            entry.signals = core::cmp::min(entry.signals + 1, entry.waiting);
+        }
+    });
+    sync_wake();
+}
+
+pub unsafe fn BroadcastConditionVariable(condvar: *mut ConditionVariableType) {
+    let _guard = sync_lock();
+    let key = initialized_condvar(condvar, "BroadcastConditionVariable") as *mut ConditionVariableType as usize;
+    condvar_waiters(|waiters| {
+        if let Some(entry) = waiters.get_mut(&key) {
+            entry.signals = entry.waiting;
+        }
+    });
+    sync_wake();
+}
+
+pub unsafe fn WaitConditionVariable(condvar: *mut ConditionVariableType, mutex: *mut MutexType) {
+    wait_condvar(condvar, mutex, None, "WaitConditionVariable");
+}
+
+pub unsafe fn TimedWaitConditionVariable(condvar: *mut ConditionVariableType, mutex: *mut MutexType, timeout: TimeSpan) -> i32 {
+    if wait_condvar(condvar, mutex, Some(deadline_after(timeout)), "TimedWaitConditionVariable") {
+        CONDITION_VARIABLE_STATUS_NO_TIMEOUT
+    } else {
+        CONDITION_VARIABLE_STATUS_TIMEOUT
+    }
+}
+
+const EVENT_CLEAR_MODE_AUTO: u8 = EventClearMode::AutoClear as u8;
+
+// Shared by Event and LightEvent. A manual clear event also releases the threads that were waiting when it
+// was signaled, even if it got cleared again before they woke up.
+fn take_event_signal(signaled: &mut bool, clear_mode: u8, broadcast_counter: u64, start_counter: u64) -> bool {
+    if *signaled {
+        if clear_mode == EVENT_CLEAR_MODE_AUTO {
+            *signaled = false;
+        }
+        true
+    } else {
+        clear_mode != EVENT_CLEAR_MODE_AUTO && broadcast_counter != start_counter
+    }
+}
+
+unsafe fn initialized_event<'a>(event: *mut EventType, function: &str) -> &'a mut EventType {
+    if event.is_null() {
+        panic!("nn::os::{}: null EventType", function);
+    }
+    let event = &mut *event;
+    if event.state != EVENT_STATE_INITIALIZED {
+        panic!("nn::os::{}: event {:p} is not initialized", function, event);
+    }
+    event
+}
+
+fn event_broadcast_counter(event: &EventType) -> u64 {
+    (event.broadcast_counter_high as u64) << 32 | event.broadcast_counter_low as u64
+}
+
+unsafe fn wait_event(event: *mut EventType, deadline: Option<Instant>, function: &str) -> bool {
+    let mut start = None;
+    sync_wait_for(deadline, |_| {
+        let event = initialized_event(event, function);
+        let counter = event_broadcast_counter(event);
+        let start = *start.get_or_insert(counter);
+        take_event_signal(&mut event.signaled, event.clear_mode, counter, start)
+    })
+}
+
+pub unsafe fn InitializeEvent(event: *mut EventType, is_signaled: bool, clear_mode: EventClearMode) {
+    if event.is_null() {
+        panic!("nn::os::InitializeEvent: null EventType");
+    }
+    let _guard = sync_lock();
+    core::ptr::write(event, EventType {
+        multi_wait_holders: [0; 2],
+        signaled: is_signaled,
+        initially_signaled: is_signaled,
+        clear_mode: clear_mode as u8,
+        state: EVENT_STATE_INITIALIZED,
+        broadcast_counter_low: 0,
+        broadcast_counter_high: 0,
+        _x1c: [0; 3]
+    });
+}
+
+pub unsafe fn FinalizeEvent(event: *mut EventType) {
+    let _guard = sync_lock();
+    initialized_event(event, "FinalizeEvent").state = EVENT_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn SignalEvent(event: *mut EventType) {
+    let _guard = sync_lock();
+    let event = initialized_event(event, "SignalEvent");
+    event.signaled = true;
+    let counter = event_broadcast_counter(event).wrapping_add(1);
+    event.broadcast_counter_low = counter as u32;
+    event.broadcast_counter_high = (counter >> 32) as u32;
+    sync_wake();
+}
+
+pub unsafe fn WaitEvent(event: *mut EventType) {
+    wait_event(event, None, "WaitEvent");
+}
+
+pub unsafe fn TryWaitEvent(event: *mut EventType) -> bool {
+    wait_event(event, Some(Instant::now()), "TryWaitEvent")
+}
+
+pub unsafe fn TimedWaitEvent(event: *mut EventType, timeout: TimeSpan) -> bool {
+    wait_event(event, Some(deadline_after(timeout)), "TimedWaitEvent")
+}
+
+pub unsafe fn ClearEvent(event: *mut EventType) {
+    let _guard = sync_lock();
+    initialized_event(event, "ClearEvent").signaled = false;
+}
+
+// The SDK keeps a light event in a few atomics, this only mirrors its size
+#[repr(C)]
+struct MockLightEventType {
+    state: u8,
+    clear_mode: u8,
+    signaled: bool,
+    _x3: u8,
+    broadcast_counter: u32,
+    _x8: u32
+}
+
+const _: () = assert!(core::mem::size_of::<MockLightEventType>() == core::mem::size_of::<LightEventType>());
+
+unsafe fn initialized_light_event<'a>(event: *mut LightEventType, function: &str) -> &'a mut MockLightEventType {
+    if event.is_null() {
+        panic!("nn::os::{}: null LightEventType", function);
+    }
+    let event = &mut *(event as *mut MockLightEventType);
+    if event.state != EVENT_STATE_INITIALIZED {
+        panic!("nn::os::{}: light event {:p} is not initialized", function, event);
+    }
+    event
+}
+
+unsafe fn wait_light_event(event: *mut LightEventType, deadline: Option<Instant>, function: &str) -> bool {
+    let mut start = None;
+    sync_wait_for(deadline, |_| {
+        let event = initialized_light_event(event, function);
+        let counter = event.broadcast_counter as u64;
+        let start = *start.get_or_insert(counter);
+        take_event_signal(&mut event.signaled, event.clear_mode, counter, start)
+    })
+}
+
+pub unsafe fn InitializeLightEvent(event: *mut LightEventType, is_signaled: bool, clear_mode: EventClearMode) {
+    if event.is_null() {
+        panic!("nn::os::InitializeLightEvent: null LightEventType");
+    }
+    let _guard = sync_lock();
+    core::ptr::write(event as *mut MockLightEventType, MockLightEventType {
+        state: EVENT_STATE_INITIALIZED,
+        clear_mode: clear_mode as u8,
+        signaled: is_signaled,
+        _x3: 0,
+        broadcast_counter: 0,
+        _x8: 0
+    });
+}
+
+pub unsafe fn FinalizeLightEvent(event: *mut LightEventType) {
+    let _guard = sync_lock();
+    initialized_light_event(event, "FinalizeLightEvent").state = EVENT_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn SignalLightEvent(event: *mut LightEventType) {
+    let _guard = sync_lock();
+    let event = initialized_light_event(event, "SignalLightEvent");
+    event.signaled = true;
+    event.broadcast_counter = event.broadcast_counter.wrapping_add(1);
+    sync_wake();
+}
+
+pub unsafe fn WaitLightEvent(event: *mut LightEventType) {
+    wait_light_event(event, None, "WaitLightEvent");
+}
+
+pub unsafe fn TryWaitLightEvent(event: *mut LightEventType) -> bool {
+    wait_light_event(event, Some(Instant::now()), "TryWaitLightEvent")
+}
+
+pub unsafe fn TimedWaitLightEvent(event: *mut LightEventType, timeout: TimeSpan) -> bool {
+    wait_light_event(event, Some(deadline_after(timeout)), "TimedWaitLightEvent")
+}
+
+pub unsafe fn ClearLightEvent(event: *mut LightEventType) {
+    let _guard = sync_lock();
+    initialized_light_event(event, "ClearLightEvent").signaled = false;
+}
+
+// A system event only holds handles, the signaled state lives in the kernel event they point to
+#[repr(C)]
+struct MockSystemEventType {
+    state: u8,
+    clear_mode: u8,
+    is_readable_handle_managed: bool,
+    is_writable_handle_managed: bool,
+    readable_handle: NativeHandle,
+    writable_handle: NativeHandle,
+    interprocess: bool,
+    _xd: [u8; 3],
+    _x10: [u32; 8]
+}
+
+const _: () = assert!(core::mem::size_of::<MockSystemEventType>() == core::mem::size_of::<SystemEventType>());
+
+impl Kernel {
+    fn create_handle(&mut self, event: usize, writable: bool) -> NativeHandle {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.handles.insert(handle, KernelHandle {
+            event,
+            writable
+        });
+        self.events.get_mut(&event).unwrap().handle_count += 1;
+        NativeHandle(handle)
+    }
+
+    fn handle(&self, handle: NativeHandle, function: &str) -> KernelHandle {
+        match self.handles.get(&handle.0) {
+            Some(handle) => *handle,
+            None => panic!("nn::os::{}: invalid handle {:#x}", function, handle.0)
+        }
+    }
+
+    fn close_handle(&mut self, handle: NativeHandle, function: &str) {
+        let event = self.handle(handle, function).event;
+        self.handles.remove(&handle.0);
+        let kernel_event = self.events.get_mut(&event).unwrap();
+        kernel_event.handle_count -= 1;
+        if kernel_event.handle_count == 0 {
+            self.events.remove(&event);
+        }
+    }
+
+    fn event(&mut self, handle: NativeHandle, function: &str) -> &mut KernelEvent {
+        let event = self.handle(handle, function).event;
+        self.events.get_mut(&event).unwrap()
+    }
+}
+
+unsafe fn initialized_system_event<'a>(event: *mut SystemEventType, function: &str) -> &'a mut MockSystemEventType {
+    if event.is_null() {
+        panic!("nn::os::{}: null SystemEventType", function);
+    }
+    let event = &mut *(event as *mut MockSystemEventType);
+    if event.state != EVENT_STATE_INITIALIZED {
+        panic!("nn::os::{}: system event {:p} is not initialized", function, event);
+    }
+    event
+}
+
+unsafe fn readable_system_event_handle(event: *mut SystemEventType, function: &str) -> NativeHandle {
+    let event = initialized_system_event(event, function);
+    if event.readable_handle == NativeHandle::INVALID {
+        panic!("nn::os::{}: system event {:p} has no readable handle", function, event);
+    }
+    event.readable_handle
+}
+
+unsafe fn wait_system_event(event: *mut SystemEventType, deadline: Option<Instant>, function: &str) -> bool {
+    sync_wait_for(deadline, |kernel| {
+        let handle = readable_system_event_handle(event, function);
+        let auto_clear = initialized_system_event(event, function).clear_mode == EVENT_CLEAR_MODE_AUTO;
+        let kernel_event = kernel.event(handle, function);
+        let signaled = kernel_event.signaled;
+        if signaled && auto_clear {
+            kernel_event.signaled = false;
+        }
+        signaled
+    })
+}
+
+unsafe fn attach_system_event(event: *mut SystemEventType, readable_handle: NativeHandle, is_readable_handle_managed: bool, writable_handle: NativeHandle, is_writable_handle_managed: bool, clear_mode: EventClearMode, function: &str) {
+    if event.is_null() {
+        panic!("nn::os::{}: null SystemEventType", function);
+    }
+    let kernel = sync_lock();
+    if readable_handle == NativeHandle::INVALID && writable_handle == NativeHandle::INVALID {
+        panic!("nn::os::{}: both handles are invalid", function);
+    }
+    if readable_handle != NativeHandle::INVALID {
+        kernel.handle(readable_handle, function);
+    }
+    if writable_handle != NativeHandle::INVALID && !kernel.handle(writable_handle, function).writable {
+        panic!("nn::os::{}: handle {:#x} is not writable", function, writable_handle.0);
+    }
+    core::ptr::write(event as *mut MockSystemEventType, MockSystemEventType {
+        state: EVENT_STATE_INITIALIZED,
+        clear_mode: clear_mode as u8,
+        is_readable_handle_managed,
+        is_writable_handle_managed,
+        readable_handle,
+        writable_handle,
+        interprocess: true,
+        _xd: [0; 3],
+        _x10: [0; 8]
+    });
+}
+
+pub unsafe fn CreateSystemEvent(event: *mut SystemEventType, clear_mode: EventClearMode, interprocess: bool) -> Result {
+    if event.is_null() {
+        panic!("nn::os::CreateSystemEvent: null SystemEventType");
+    }
+    let mut kernel = sync_lock();
+    let id = kernel.next_event;
+    kernel.next_event += 1;
+    kernel.events.insert(id, KernelEvent {
+        signaled: false,
+        handle_count: 0
+    });
+    let readable_handle = kernel.create_handle(id, false);
+    let writable_handle = kernel.create_handle(id, true);
+    core::ptr::write(event as *mut MockSystemEventType, MockSystemEventType {
+        state: EVENT_STATE_INITIALIZED,
+        clear_mode: clear_mode as u8,
+        is_readable_handle_managed: true,
+        is_writable_handle_managed: true,
+        readable_handle,
+        writable_handle,
+        interprocess,
+        _xd: [0; 3],
+        _x10: [0; 8]
+    });
+    Result::SUCCESS
+}
+
+pub unsafe fn AttachSystemEvent(event: *mut SystemEventType, readable_handle: NativeHandle, is_readable_handle_managed: bool, writable_handle: NativeHandle, is_writable_handle_managed: bool, clear_mode: EventClearMode) {
+    attach_system_event(event, readable_handle, is_readable_handle_managed, writable_handle, is_writable_handle_managed, clear_mode, "AttachSystemEvent");
+}
+
+pub unsafe fn AttachReadableHandleToSystemEvent(event: *mut SystemEventType, readable_handle: NativeHandle, is_managed: bool, clear_mode: EventClearMode) {
+    attach_system_event(event, readable_handle, is_managed, NativeHandle::INVALID, false, clear_mode, "AttachReadableHandleToSystemEvent");
+}
+
+pub unsafe fn DestroySystemEvent(event: *mut SystemEventType) {
+    let mut kernel = sync_lock();
+    let event = initialized_system_event(event, "DestroySystemEvent");
+    if event.is_readable_handle_managed && event.readable_handle != NativeHandle::INVALID {
+        kernel.close_handle(event.readable_handle, "DestroySystemEvent");
+    }
+    if event.is_writable_handle_managed && event.writable_handle != NativeHandle::INVALID {
+        kernel.close_handle(event.writable_handle, "DestroySystemEvent");
+    }
+    event.state = EVENT_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn SignalSystemEvent(event: *mut SystemEventType) {
+    let mut kernel = sync_lock();
+    let event = initialized_system_event(event, "SignalSystemEvent");
+    if event.writable_handle == NativeHandle::INVALID {
+        panic!("nn::os::SignalSystemEvent: system event {:p} has no writable handle", event);
+    }
+    kernel.event(event.writable_handle, "SignalSystemEvent").signaled = true;
+    sync_wake();
+}
+
+pub unsafe fn WaitSystemEvent(event: *mut SystemEventType) {
+    wait_system_event(event, None, "WaitSystemEvent");
+}
+
+pub unsafe fn TryWaitSystemEvent(event: *mut SystemEventType) -> bool {
+    wait_system_event(event, Some(Instant::now()), "TryWaitSystemEvent")
+}
+
+pub unsafe fn TimedWaitSystemEvent(event: *mut SystemEventType, timeout: TimeSpan) -> bool {
+    wait_system_event(event, Some(deadline_after(timeout)), "TimedWaitSystemEvent")
+}
+
+pub unsafe fn ClearSystemEvent(event: *mut SystemEventType) {
+    let mut kernel = sync_lock();
+    let handle = readable_system_event_handle(event, "ClearSystemEvent");
+    kernel.event(handle, "ClearSystemEvent").signaled = false;
+}
+
+pub unsafe fn GetReadableHandleOfSystemEvent(event: *const SystemEventType) -> NativeHandle {
+    let _guard = sync_lock();
+    let event = initialized_system_event(event as *mut SystemEventType, "GetReadableHandleOfSystemEvent");
+    if !event.interprocess {
+        panic!("nn::os::GetReadableHandleOfSystemEvent: system event {:p} isn't interprocess", event);
+    }
+    event.readable_handle
+}
+
+pub unsafe fn GetWritableHandleOfSystemEvent(event: *const SystemEventType) -> NativeHandle {
+    let _guard = sync_lock();
+    let event = initialized_system_event(event as *mut SystemEventType, "GetWritableHandleOfSystemEvent");
+    if !event.interprocess {
+        panic!("nn::os::GetWritableHandleOfSystemEvent: system event {:p} isn't interprocess", event);
+    }
+    event.writable_handle
+}
+
+#[repr(C)]
+struct MockSemaphoreType {
+    state: u8,
+    _x1: [u8; 3],
+    count: i32,
+    max_count: i32,
+    _xc: [u32; 7]
+}
+
+const _: () = assert!(core::mem::size_of::<MockSemaphoreType>() == core::mem::size_of::<SemaphoreType>());
+
+const SEMAPHORE_STATE_UNINITIALIZED: u8 = 0;
+const SEMAPHORE_STATE_INITIALIZED: u8 = 1;
+
+unsafe fn initialized_semaphore<'a>(semaphore: *const SemaphoreType, function: &str) -> &'a mut MockSemaphoreType {
+    if semaphore.is_null() {
+        panic!("nn::os::{}: null SemaphoreType", function);
+    }
+    let semaphore = &mut *(semaphore as *mut MockSemaphoreType);
+    if semaphore.state != SEMAPHORE_STATE_INITIALIZED {
+        panic!("nn::os::{}: semaphore {:p} is not initialized", function, semaphore);
+    }
+    semaphore
+}
+
+unsafe fn acquire_semaphore(semaphore: *mut SemaphoreType, deadline: Option<Instant>, function: &str) -> bool {
+    sync_wait_for(deadline, |_| {
+        let semaphore = initialized_semaphore(semaphore, function);
+        if semaphore.count > 0 {
+            semaphore.count -= 1;
+            true
+        } else {
+            false
+        }
+    })
+}
+
+pub unsafe fn InitializeSemaphore(semaphore: *mut SemaphoreType, initial_count: i32, max_count: i32) {
+    if semaphore.is_null() {
+        panic!("nn::os::InitializeSemaphore: null SemaphoreType");
+    }
+    if max_count < 1 || initial_count < 0 || initial_count > max_count {
+        panic!("nn::os::InitializeSemaphore: invalid counts {} out of {}", initial_count, max_count);
+    }
+    let _guard = sync_lock();
+    core::ptr::write(semaphore as *mut MockSemaphoreType, MockSemaphoreType {
+        state: SEMAPHORE_STATE_INITIALIZED,
+        _x1: [0; 3],
+        count: initial_count,
+        max_count,
+        _xc: [0; 7]
+    });
+}
+
+pub unsafe fn FinalizeSemaphore(semaphore: *mut SemaphoreType) {
+    let _guard = sync_lock();
+    initialized_semaphore(semaphore, "FinalizeSemaphore").state = SEMAPHORE_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn AcquireSemaphore(semaphore: *mut SemaphoreType) {
+    acquire_semaphore(semaphore, None, "AcquireSemaphore");
+}
+
+pub unsafe fn TryAcquireSemaphore(semaphore: *mut SemaphoreType) -> bool {
+    acquire_semaphore(semaphore, Some(Instant::now()), "TryAcquireSemaphore")
+}
+
+pub unsafe fn TimedAcquireSemaphore(semaphore: *mut SemaphoreType, timeout: TimeSpan) -> bool {
+    acquire_semaphore(semaphore, Some(deadline_after(timeout)), "TimedAcquireSemaphore")
+}
+
+pub unsafe fn ReleaseSemaphore(semaphore: *mut SemaphoreType) {
+    ReleaseSemaphoreCount(semaphore, 1);
+}
+
+pub unsafe fn ReleaseSemaphoreCount(semaphore: *mut SemaphoreType, count: i32) {
+    let _guard = sync_lock();
+    let semaphore = initialized_semaphore(semaphore, "ReleaseSemaphore");
+    if count < 1 || count > semaphore.max_count - semaphore.count {
+        panic!("nn::os::ReleaseSemaphore: releasing {} would take semaphore {:p} past its max count {}", count, semaphore, semaphore.max_count);
+    }
+    semaphore.count += count;
+    sync_wake();
+}
+
+pub unsafe fn GetCurrentSemaphoreCount(semaphore: *const SemaphoreType) -> i32 {
+    let _guard = sync_lock();
+    initialized_semaphore(semaphore, "GetCurrentSemaphoreCount").count
+}
+
+// A ring buffer over the caller's array
+#[repr(C)]
+struct MockMessageQueueType {
+    buffer: *mut usize,
+    capacity: usize,
+    count: usize,
+    offset: usize,
+    state: u8,
+    _x21: [u8; 7],
+    _x28: [u64; 5]
+}
+
+const _: () = assert!(core::mem::size_of::<MockMessageQueueType>() == core::mem::size_of::<MessageQueueType>());
+
+const MESSAGE_QUEUE_STATE_UNINITIALIZED: u8 = 0;
+const MESSAGE_QUEUE_STATE_INITIALIZED: u8 = 1;
+
+unsafe fn initialized_message_queue<'a>(queue: *mut MessageQueueType, function: &str) -> &'a mut MockMessageQueueType {
+    if queue.is_null() {
+        panic!("nn::os::{}: null MessageQueueType", function);
+    }
+    let queue = &mut *(queue as *mut MockMessageQueueType);
+    if queue.state != MESSAGE_QUEUE_STATE_INITIALIZED {
+        panic!("nn::os::{}: message queue {:p} is not initialized", function, queue);
+    }
+    queue
+}
+
+unsafe fn send_message(queue: *mut MessageQueueType, data: usize, deadline: Option<Instant>, function: &str) -> bool {
+    let sent = sync_wait_for(deadline, |_| {
+        let queue = initialized_message_queue(queue, function);
+        if queue.count == queue.capacity {
+            return false;
+        }
+        *queue.buffer.add((queue.offset + queue.count) % queue.capacity) = data;
+        queue.count += 1;
+        true
+    });
+    if sent {
+        sync_wake();
+    }
+    sent
+}
+
+unsafe fn receive_message(out: *mut usize, queue: *mut MessageQueueType, deadline: Option<Instant>, function: &str) -> bool {
+    if out.is_null() {
+        panic!("nn::os::{}: null output", function);
+    }
+    let received = sync_wait_for(deadline, |_| {
+        let queue = initialized_message_queue(queue, function);
+        if queue.count == 0 {
+            return false;
+        }
+        *out = *queue.buffer.add(queue.offset);
+        queue.offset = (queue.offset + 1) % queue.capacity;
+        queue.count -= 1;
+        true
+    });
+    if received {
+        sync_wake();
+    }
+    received
+}
+
+pub unsafe fn InitializeMessageQueue(queue: *mut MessageQueueType, buffer: *mut usize, count: usize) {
+    if queue.is_null() {
+        panic!("nn::os::InitializeMessageQueue: null MessageQueueType");
+    }
+    if buffer.is_null() || count == 0 {
+        panic!("nn::os::InitializeMessageQueue: invalid buffer {:p} of {} messages", buffer, count);
+    }
+    let _guard = sync_lock();
+    core::ptr::write(queue as *mut MockMessageQueueType, MockMessageQueueType {
+        buffer,
+        capacity: count,
+        count: 0,
+        offset: 0,
+        state: MESSAGE_QUEUE_STATE_INITIALIZED,
+        _x21: [0; 7],
+        _x28: [0; 5]
+    });
+}
+
+pub unsafe fn FinalizeMessageQueue(queue: *mut MessageQueueType) {
+    let _guard = sync_lock();
+    initialized_message_queue(queue, "FinalizeMessageQueue").state = MESSAGE_QUEUE_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn SendMessageQueue(queue: *mut MessageQueueType, data: usize) {
+    send_message(queue, data, None, "SendMessageQueue");
+}
+
+pub unsafe fn TrySendMessageQueue(queue: *mut MessageQueueType, data: usize) -> bool {
+    send_message(queue, data, Some(Instant::now()), "TrySendMessageQueue")
+}
+
+pub unsafe fn TimedSendMessageQueue(queue: *mut MessageQueueType, data: usize, timeout: TimeSpan) -> bool {
+    send_message(queue, data, Some(deadline_after(timeout)), "TimedSendMessageQueue")
+}
+
+pub unsafe fn ReceiveMessageQueue(out: *mut usize, queue: *mut MessageQueueType) {
+    receive_message(out, queue, None, "ReceiveMessageQueue");
+}
+
+pub unsafe fn TryReceiveMessageQueue(out: *mut usize, queue: *mut MessageQueueType) -> bool {
+    receive_message(out, queue, Some(Instant::now()), "TryReceiveMessageQueue")
+}
+
+pub unsafe fn TimedReceiveMessageQueue(out: *mut usize, queue: *mut MessageQueueType, timeout: TimeSpan) -> bool {
+    receive_message(out, queue, Some(deadline_after(timeout)), "TimedReceiveMessageQueue")
+}
+
+// Multi wait
+
+// Holders are kept in an intrusive list in the order they were linked, WaitAny returns the first one that's ready
+#[repr(C)]
+struct MockMultiWaitType {
+    state: u8,
+    _x1: [u8; 7],
+    first: *mut MockMultiWaitHolderType,
+    _x10: [u64; 0x3E]
+}
+
+const _: () = assert!(core::mem::size_of::<MockMultiWaitType>() == core::mem::size_of::<MultiWaitType>());
+
+#[repr(C)]
+struct MockMultiWaitHolderType {
+    kind: HolderKind,
+    wait_type: MessageQueueWaitType,
+    object: *mut c_void,
+    multi_wait: *mut MockMultiWaitType,
+    next: *mut MockMultiWaitHolderType,
+    user_data: usize,
+    _x28: [u64; 3]
+}
+
+const _: () = assert!(core::mem::size_of::<MockMultiWaitHolderType>() == core::mem::size_of::<MultiWaitHolderType>());
+
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum HolderKind {
+    Uninitialized,
+    Event,
+    SystemEvent,
+    Thread,
+    Semaphore,
+    MessageQueue
+}
+
+const MULTI_WAIT_STATE_UNINITIALIZED: u8 = 0;
+const MULTI_WAIT_STATE_INITIALIZED: u8 = 1;
+
+unsafe fn initialized_multi_wait<'a>(multi_wait: *mut MultiWaitType, function: &str) -> &'a mut MockMultiWaitType {
+    if multi_wait.is_null() {
+        panic!("nn::os::{}: null MultiWaitType", function);
+    }
+    let multi_wait = &mut *(multi_wait as *mut MockMultiWaitType);
+    if multi_wait.state != MULTI_WAIT_STATE_INITIALIZED {
+        panic!("nn::os::{}: multi wait {:p} is not initialized", function, multi_wait);
+    }
+    multi_wait
+}
+
+unsafe fn initialized_holder<'a>(holder: *mut MultiWaitHolderType, function: &str) -> &'a mut MockMultiWaitHolderType {
+    if holder.is_null() {
+        panic!("nn::os::{}: null MultiWaitHolderType", function);
+    }
+    let holder = &mut *(holder as *mut MockMultiWaitHolderType);
+    if holder.kind == HolderKind::Uninitialized {
+        panic!("nn::os::{}: holder {:p} is not initialized", function, holder);
+    }
+    holder
+}
+
+unsafe fn initialize_holder(holder: *mut MultiWaitHolderType, kind: HolderKind, object: *mut c_void, wait_type: MessageQueueWaitType, function: &str) {
+    if holder.is_null() {
+        panic!("nn::os::{}: null MultiWaitHolderType", function);
+    }
+    if object.is_null() {
+        panic!("nn::os::{}: null object", function);
+    }
+    let _guard = sync_lock();
+    core::ptr::write(holder as *mut MockMultiWaitHolderType, MockMultiWaitHolderType {
+        kind,
+        wait_type,
+        object,
+        multi_wait: core::ptr::null_mut(),
+        next: core::ptr::null_mut(),
+        user_data: 0,
+        _x28: [0; 3]
+    });
+}
+
+// Like the SDK, nothing is consumed: an auto clear event stays signaled and the semaphore keeps its count
+unsafe fn holder_ready(kernel: &mut Kernel, holder: &MockMultiWaitHolderType) -> bool {
+    match holder.kind {
+        HolderKind::Event => initialized_event(holder.object as *mut EventType, "WaitAny").signaled,
+        HolderKind::SystemEvent => {
+            let handle = readable_system_event_handle(holder.object as *mut SystemEventType, "WaitAny");
+            kernel.event(handle, "WaitAny").signaled
+        },
+        HolderKind::Thread => thread_state(holder.object as *const ThreadType).exited.load(Ordering::Acquire),
+        HolderKind::Semaphore => initialized_semaphore(holder.object as *const SemaphoreType, "WaitAny").count > 0,
+        HolderKind::MessageQueue => {
+            let queue = initialized_message_queue(holder.object as *mut MessageQueueType, "WaitAny");
+            match holder.wait_type {
+                MessageQueueWaitType::ForNotFull => queue.count < queue.capacity,
+                MessageQueueWaitType::ForNotEmpty => queue.count > 0
+            }
+        },
+        HolderKind::Uninitialized => false
+    }
+}
+
+unsafe fn wait_any(multi_wait: *mut MultiWaitType, deadline: Option<Instant>, function: &str) -> *mut MultiWaitHolderType {
+    let mut fired = core::ptr::null_mut();
+    sync_wait_for(deadline, |kernel| {
+        let mut holder = initialized_multi_wait(multi_wait, function).first;
+        while !holder.is_null() {
+            if holder_ready(kernel, &*holder) {
+                fired = holder as *mut MultiWaitHolderType;
+                return true;
+            }
+            holder = (*holder).next;
+        }
+        false
+    });
+    fired
+}
+
+pub unsafe fn InitializeMultiWait(multi_wait: *mut MultiWaitType) {
+    if multi_wait.is_null() {
+        panic!("nn::os::InitializeMultiWait: null MultiWaitType");
+    }
+    let _guard = sync_lock();
+    core::ptr::write(multi_wait as *mut MockMultiWaitType, MockMultiWaitType {
+        state: MULTI_WAIT_STATE_INITIALIZED,
+        _x1: [0; 7],
+        first: core::ptr::null_mut(),
+        _x10: [0; 0x3E]
+    });
+}
+
+pub unsafe fn FinalizeMultiWait(multi_wait: *mut MultiWaitType) {
+    let _guard = sync_lock();
+    let multi_wait = initialized_multi_wait(multi_wait, "FinalizeMultiWait");
+    if !multi_wait.first.is_null() {
+        panic!("nn::os::FinalizeMultiWait: multi wait {:p} still has holders linked", multi_wait);
+    }
+    multi_wait.state = MULTI_WAIT_STATE_UNINITIALIZED;
+}
+
+pub unsafe fn InitializeMultiWaitHolderEvent(holder: *mut MultiWaitHolderType, event: *mut EventType) {
+    initialize_holder(holder, HolderKind::Event, event as _, MessageQueueWaitType::ForNotEmpty, "InitializeMultiWaitHolder");
+}
+
+pub unsafe fn InitializeMultiWaitHolderSystemEvent(holder: *mut MultiWaitHolderType, event: *mut SystemEventType) {
+    initialize_holder(holder, HolderKind::SystemEvent, event as _, MessageQueueWaitType::ForNotEmpty, "InitializeMultiWaitHolder");
+}
+
+pub unsafe fn InitializeMultiWaitHolderThread(holder: *mut MultiWaitHolderType, thread: *mut ThreadType) {
+    initialize_holder(holder, HolderKind::Thread, thread as _, MessageQueueWaitType::ForNotEmpty, "InitializeMultiWaitHolder");
+}
+
+pub unsafe fn InitializeMultiWaitHolderSemaphore(holder: *mut MultiWaitHolderType, semaphore: *mut SemaphoreType) {
+    initialize_holder(holder, HolderKind::Semaphore, semaphore as _, MessageQueueWaitType::ForNotEmpty, "InitializeMultiWaitHolder");
+}
+
+pub unsafe fn InitializeMultiWaitHolderMessageQueue(holder: *mut MultiWaitHolderType, queue: *mut MessageQueueType, wait_type: MessageQueueWaitType) {
+    initialize_holder(holder, HolderKind::MessageQueue, queue as _, wait_type, "InitializeMultiWaitHolder");
+}
+
+pub unsafe fn FinalizeMultiWaitHolder(holder: *mut MultiWaitHolderType) {
+    let _guard = sync_lock();
+    let holder = initialized_holder(holder, "FinalizeMultiWaitHolder");
+    if !holder.multi_wait.is_null() {
+        panic!("nn::os::FinalizeMultiWaitHolder: holder {:p} is still linked", holder);
+    }
+    holder.kind = HolderKind::Uninitialized;
+}
+
+pub unsafe fn LinkMultiWaitHolder(multi_wait: *mut MultiWaitType, holder: *mut MultiWaitHolderType) {
+    let _guard = sync_lock();
+    let multi_wait = initialized_multi_wait(multi_wait, "LinkMultiWaitHolder");
+    let holder = initialized_holder(holder, "LinkMultiWaitHolder");
+    if !holder.multi_wait.is_null() {
+        panic!("nn::os::LinkMultiWaitHolder: holder {:p} is already linked", holder);
+    }
+    let mut link = &mut multi_wait.first;
+    while !link.is_null() {
+        link = &mut (**link).next;
+    }
+    *link = holder;
+    holder.multi_wait = multi_wait;
+}
+
+pub unsafe fn UnlinkMultiWaitHolder(holder: *mut MultiWaitHolderType) {
+    let _guard = sync_lock();
+    let holder = initialized_holder(holder, "UnlinkMultiWaitHolder");
+    if holder.multi_wait.is_null() {
+        panic!("nn::os::UnlinkMultiWaitHolder: holder {:p} isn't linked", holder);
+    }
+    let mut link = &mut (*holder.multi_wait).first;
+    while !core::ptr::eq(*link, holder as *mut MockMultiWaitHolderType) {
+        link = &mut (**link).next;
+    }
+    *link = holder.next;
+    holder.multi_wait = core::ptr::null_mut();
+    holder.next = core::ptr::null_mut();
+}
+
+pub unsafe fn UnlinkAllMultiWaitHolder(multi_wait: *mut MultiWaitType) {
+    let _guard = sync_lock();
+    let multi_wait = initialized_multi_wait(multi_wait, "UnlinkAllMultiWaitHolder");
+    let mut holder = core::mem::replace(&mut multi_wait.first, core::ptr::null_mut());
+    while !holder.is_null() {
+        let next = (*holder).next;
+        (*holder).multi_wait = core::ptr::null_mut();
+        (*holder).next = core::ptr::null_mut();
+        holder = next;
+    }
+}
+
+pub unsafe fn WaitAny(multi_wait: *mut MultiWaitType) -> *mut MultiWaitHolderType {
+    wait_any(multi_wait, None, "WaitAny")
+}
+
+pub unsafe fn TryWaitAny(multi_wait: *mut MultiWaitType) -> *mut MultiWaitHolderType {
+    wait_any(multi_wait, Some(Instant::now()), "TryWaitAny")
+}
+
+pub unsafe fn TimedWaitAny(multi_wait: *mut MultiWaitType, timeout: TimeSpan) -> *mut MultiWaitHolderType {
+    wait_any(multi_wait, Some(deadline_after(timeout)), "TimedWaitAny")
+}
+
+pub unsafe fn SetMultiWaitHolderUserData(holder: *mut MultiWaitHolderType, user_data: usize) {
+    let _guard = sync_lock();
+    initialized_holder(holder, "SetMultiWaitHolderUserData").user_data = user_data;
+}
+
+pub unsafe fn GetMultiWaitHolderUserData(holder: *const MultiWaitHolderType) -> usize {
+    let _guard = sync_lock();
+    initialized_holder(holder as *mut MultiWaitHolderType, "GetMultiWaitHolderUserData").user_data
+}
+
+// Other OS stuff
+
+struct MemoryHeap {
+    base: usize,
+    size: usize,
+    // Blocks are handed out front to back and never returned, FreeMemoryBlock isn't bound
+    used: usize
+}
+
+static MEMORY_HEAP: Mutex<MemoryHeap> = Mutex::new(MemoryHeap {
+    base: 0,
+    size: 0,
+    used: 0
+});
+
+pub unsafe fn SetMemoryHeapSize(size: usize) -> Result {
+    if !size.is_multiple_of(MEMORY_HEAP_UNIT_SIZE) {
+        panic!("nn::os::SetMemoryHeapSize: size {:#x} is not a multiple of {:#x}", size, MEMORY_HEAP_UNIT_SIZE);
+    }
+    let mut heap = MEMORY_HEAP.lock().unwrap_or_else(|e| e.into_inner());
+    if size > MEMORY_HEAP_RESERVE_SIZE {
+        return result::OUT_OF_MEMORY;
+    }
+    if size < heap.used {
+        return result::BUSY;
+    }
+    if heap.base == 0 && size != 0 {
+        let layout = Layout::from_size_align(MEMORY_HEAP_RESERVE_SIZE, MEMORY_BLOCK_UNIT_SIZE).unwrap();
+        let base = alloc(layout);
+        if base.is_null() {
+            return result::OUT_OF_MEMORY;
+        }
+        heap.base = base as usize;
+    }
+    heap.size = size;
+    Result::SUCCESS
+}
+
+pub unsafe fn AllocateMemoryBlock(address: *mut *mut c_void, size: usize) -> Result {
+    if size == 0 || !size.is_multiple_of(MEMORY_BLOCK_UNIT_SIZE) {
+        panic!("nn::os::AllocateMemoryBlock: size {:#x} is not a multiple of {:#x}", size, MEMORY_BLOCK_UNIT_SIZE);
+    }
+    let mut heap = MEMORY_HEAP.lock().unwrap_or_else(|e| e.into_inner());
+    if heap.size - heap.used < size {
+        return result::OUT_OF_MEMORY;
+    }
+    *address = (heap.base + heap.used) as *mut c_void;
+    heap.used += size;
+    Result::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use std::boxed::Box;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+    use std::vec::Vec;
+    use libc::c_void;
+    use super::super::super::TimeSpan;
+    use super::super::sync::{Condvar, Event, EventClearMode, LightEvent, MessageQueue, MessageQueueWaitType, MultiWait, Mutex, ReentrantMutex, Semaphore};
+    use super::super::thread::{self, Builder};
+    use super::super::tls::{AccessError, TlsSlot};
+    use super::super::{alloc_from_heap, result, set_heap_size, Thread};
+    use super::{MEMORY_BLOCK_UNIT_SIZE, MEMORY_HEAP_RESERVE_SIZE, MEMORY_HEAP_UNIT_SIZE};
+
+    #[repr(align(0x1000))]
+    struct Stack([u8; 0x4000]);
+
+    extern "C" fn add_one(arg: *mut c_void) {
+        let counter = unsafe { &*(arg as *const AtomicUsize) };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn threads_run_on_start() {
+        let counter = AtomicUsize::new(0);
+        let mut stack = Box::new(Stack([0; 0x4000]));
+        let arg = &counter as *const AtomicUsize as *mut c_void;
+        let mut thread = Thread::new(add_one, arg, stack.0.as_mut_ptr() as _, stack.0.len(), Thread::PRIORITY_DEFAULT).unwrap();
+        assert_eq!(thread.get_original_priority(), Thread::PRIORITY_DEFAULT);
+        assert_eq!(thread.set_priority(Thread::PRIORITY_MAX), Thread::PRIORITY_DEFAULT);
+        assert_eq!(thread.get_current_priority(), Thread::PRIORITY_MAX);
+
+        // Nothing runs until the thread is started
+        Thread::sleep(TimeSpan::from_millis(10));
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        thread.start();
+        thread.wait();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        // Waiting again on an exited thread returns right away
+        thread.wait();
+        thread.destroy();
+    }
+
+    #[test]
+    fn default_thread_names() {
+        let mut stack = Box::new(Stack([0; 0x4000]));
+        let counter = AtomicUsize::new(0);
+        let arg = &counter as *const AtomicUsize as *mut c_void;
+        let thread = Thread::new_on_core(add_one, arg, stack.0.as_mut_ptr() as _, stack.0.len(), Thread::PRIORITY_MIN, 1).unwrap();
+        assert!(thread.get_name().starts_with("Thread_0x"));
+        // Threads nn::os didn't create go by their host name, cut to fit the SDK's buffer
+        let name = Thread::current().get_name();
+        let host = std::thread::current();
+        let host_name = match host.name() {
+            Some("main") => "MainThread",
+            host_name => host_name.unwrap()
+        };
+        assert!(name.len() < Thread::MAX_NAME_LEN);
+        assert!(host_name.starts_with(&name));
+        thread.destroy();
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn rejects_bad_priorities() {
+        let mut stack = Box::new(Stack([0; 0x4000]));
+        let _ = Thread::new(add_one, core::ptr::null_mut(), stack.0.as_mut_ptr() as _, stack.0.len(), Thread::PRIORITY_MIN + 1);
+    }
+
+    #[test]
+    fn threads_run_and_return() {
+        let handle = Builder::new().name("worker").spawn(|| {
+            (Thread::current().get_name(), 6 * 7)
+        }).unwrap();
+        let (name, value) = handle.join().unwrap();
+        assert_eq!(name, "worker");
+        assert_eq!(value, 42);
+
+        let handles: Vec<_> = (0..4).map(|i| thread::spawn(move || i * 2)).collect();
+        let results: Vec<_> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(results, [0, 2, 4, 6]);
+
+        assert_eq!(Builder::new().priority(Thread::PRIORITY_MIN + 1).spawn(|| ()).err(), Some(thread::SpawnError::InvalidPriority(Thread::PRIORITY_MIN + 1)));
+        assert_eq!(Builder::new().core(thread::CORE_COUNT).spawn(|| ()).err(), Some(thread::SpawnError::InvalidCore(thread::CORE_COUNT)));
+    }
+
+    #[test]
+    fn dropping_a_join_handle_waits() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        {
+            let finished = finished.clone();
+            drop(thread::spawn(move || {
+                thread::sleep(TimeSpan::from_millis(30));
+                finished.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+
+        let go = Arc::new(AtomicBool::new(false));
+        {
+            let go = go.clone();
+            let finished = finished.clone();
+            thread::spawn(move || {
+                while !go.load(Ordering::SeqCst) {
+                    thread::yield_now();
+                }
+                finished.fetch_add(1, Ordering::SeqCst);
+            }).detach();
+        }
+        // Still spinning on go, detach didn't wait for it
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+        go.store(true, Ordering::SeqCst);
+        let start = Instant::now();
+        while finished.load(Ordering::SeqCst) != 2 {
+            assert!(start.elapsed().as_secs() < 5);
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn owned_thread_names() {
+        extern "C" fn main(_: *mut c_void) {}
+
+        let mut stack = Box::new(Stack([0; 0x4000]));
+        let mut thread = Thread::new(main, core::ptr::null_mut(), stack.0.as_mut_ptr() as _, stack.0.len(), Thread::PRIORITY_DEFAULT).unwrap();
+        let long = "a thread name well past the 32 byte buffer";
+        thread.set_name(long);
+        assert_eq!(thread.get_name(), long);
+        // Going through the handle points the ThreadType back at its buffer, the Thread still frees the long one
+        (*thread).set_name(long);
+        assert_eq!(thread.get_name(), &long[..Thread::MAX_NAME_LEN - 1]);
+        thread.set_name("short");
+        assert_eq!(thread.get_name(), "short");
+        thread.set_name(long);
+        thread.start();
+        thread.wait();
+        assert_eq!(thread.get_name(), long);
+        drop(thread);
+    }
+
+    #[test]
+    fn tls_generations_and_destructors() {
+        static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn destructor(value: usize) {
+            DESTROYED.fetch_add(value, Ordering::SeqCst);
+        }
+
+        let slot = TlsSlot::allocate(Some(destructor)).unwrap();
+        slot.set(1);
+        thread::spawn(move || {
+            assert_eq!(slot.get(), 0);
+            slot.set(10);
+        }).join().unwrap();
+        // Only the thread created through nn::os ran its destructor
+        assert_eq!(DESTROYED.load(Ordering::SeqCst), 10);
+        assert_eq!(slot.get(), 1);
+
+        let index = slot.as_raw();
+        slot.free();
+        let slot = TlsSlot::allocate(None).unwrap();
+        if slot.as_raw() == index {
+            // The old owner's value doesn't leak into the new one
+            assert_eq!(slot.get(), 0);
+        }
+        slot.free();
+    }
+
+    #[test]
+    fn local_key_values_are_dropped_per_thread() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        static REVIVED: AtomicUsize = AtomicUsize::new(0);
+        struct Counted(core::cell::Cell<usize>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(self.0.get(), Ordering::SeqCst);
+                // The key can't be initialized again while its value is being dropped
+                if KEY.try_with(|_| ()).err() != Some(AccessError::Destroyed) {
+                    REVIVED.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        }
+        crate::nn_thread_local! {
+            static KEY: Counted = Counted(core::cell::Cell::new(0));
+        }
+
+        let handles: Vec<_> = (1..=3).map(|i| thread::spawn(move || KEY.with(|value| value.0.set(i)))).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 6);
+        assert_eq!(REVIVED.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn contended_mutex_counter() {
+        let counter = Arc::new(Mutex::new(0u64));
+        let handles: Vec<_> = (0..8).map(|_| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                for _ in 0..1000 {
+                    *counter.lock() += 1;
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*counter.lock(), 8000);
+        assert!(!counter.is_locked_by_current_thread());
+    }
+
+    #[test]
+    fn lock_levels() {
+        let outer = Mutex::with_lock_level(1, 1);
+        let inner = ReentrantMutex::with_lock_level(31, 31);
+        let _outer = outer.lock();
+        assert_eq!(*inner.lock(), 31);
+    }
+
+    #[test]
+    #[should_panic(expected = "lock level 0 is out of range 1..=31")]
+    fn lock_level_zero_is_rejected() {
+        Mutex::with_lock_level((), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "lock level 32 is out of range 1..=31")]
+    fn lock_level_32_is_rejected() {
+        ReentrantMutex::with_lock_level((), 32);
+    }
+
+    #[test]
+    fn reentrant_mutex() {
+        let mutex = ReentrantMutex::new(5);
+        let outer = mutex.lock();
+        let inner = mutex.lock();
+        assert_eq!(*outer + *inner, 10);
+        assert!(mutex.is_locked_by_current_thread());
+        drop(inner);
+        drop(outer);
+        assert!(!mutex.is_locked_by_current_thread());
+    }
+
+    #[test]
+    fn condvar_producer_consumer() {
+        let shared = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
+        let consumer = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let (queue, condvar) = &*shared;
+                let mut sum = 0;
+                let mut received = 0;
+                while received < 100 {
+                    let mut guard = condvar.wait_while(queue.lock(), |queue: &mut Vec<u32>| queue.is_empty());
+                    for value in guard.drain(..) {
+                        sum += value;
+                        received += 1;
+                    }
+                }
+                sum
+            })
+        };
+        let (queue, condvar) = &*shared;
+        for i in 0..100 {
+            queue.lock().push(i);
+            condvar.notify_one();
+        }
+        assert_eq!(consumer.join().unwrap(), (0..100).sum::<u32>());
+    }
+
+    #[test]
+    fn timeouts_expire() {
+        let timeout = TimeSpan::from_millis(30);
+
+        let mutex = Mutex::new(());
+        let condvar = Condvar::new();
+        let start = Instant::now();
+        let (_guard, result) = condvar.wait_timeout(mutex.lock(), timeout);
+        assert!(result.timed_out());
+        assert!(start.elapsed().as_millis() >= 30);
+
+        let start = Instant::now();
+        assert!(!Event::new(false, EventClearMode::AutoClear).wait_timeout(timeout));
+        assert!(!LightEvent::new(false, EventClearMode::ManualClear).wait_timeout(timeout));
+        assert!(!Semaphore::new(0, 1).acquire_timeout(timeout));
+        assert_eq!(MessageQueue::new(1).receive_timeout(timeout), None);
+        assert!(start.elapsed().as_millis() >= 120);
+    }
+
+    #[test]
+    fn auto_clear_event_wakes_one_waiter() {
+        let event = Arc::new(Event::new(false, EventClearMode::AutoClear));
+        let waiters: Vec<_> = (0..3).map(|_| {
+            let event = event.clone();
+            thread::spawn(move || event.wait_timeout(TimeSpan::from_millis(300)))
+        }).collect();
+        thread::sleep(TimeSpan::from_millis(50));
+        event.signal();
+        let woken = waiters.into_iter().map(|waiter| waiter.join().unwrap()).filter(|&woken| woken).count();
+        assert_eq!(woken, 1);
+        assert!(!event.try_wait());
+    }
+
+    #[test]
+    fn manual_clear_event_wakes_every_waiter() {
+        let event = Arc::new(Event::new(false, EventClearMode::ManualClear));
+        let waiters: Vec<_> = (0..3).map(|_| {
+            let event = event.clone();
+            thread::spawn(move || event.wait_timeout(TimeSpan::from_millis(300)))
+        }).collect();
+        thread::sleep(TimeSpan::from_millis(50));
+        event.signal();
+        assert!(waiters.into_iter().all(|waiter| waiter.join().unwrap()));
+        assert!(event.try_wait());
+        event.clear();
+        assert!(!event.try_wait());
+    }
+
+    #[test]
+    fn semaphore_and_message_queue() {
+        let semaphore = Semaphore::new(1, 3);
+        assert!(semaphore.try_acquire());
+        assert!(!semaphore.try_acquire());
+        semaphore.release_count(3);
+        assert_eq!(semaphore.count(), 3);
+
+        let queue = MessageQueue::new(2);
+        assert!(queue.try_send(1));
+        assert!(queue.try_send(2));
+        assert!(!queue.try_send(3));
+        assert_eq!(queue.receive(), 1);
+        assert!(queue.try_send(3));
+        assert_eq!(queue.try_receive(), Some(2));
+        assert_eq!(queue.try_receive(), Some(3));
+        assert_eq!(queue.try_receive(), None);
+    }
+
+    // Where the SDK would abort
+    #[test]
+    #[should_panic(expected = "past its max count 3")]
+    fn semaphore_release_past_max() {
+        Semaphore::new(2, 3).release_count(2);
+    }
+
+    #[test]
+    fn multi_wait_returns_the_signaled_holder() {
+        let quiet = Event::new(false, EventClearMode::AutoClear);
+        let event = Arc::new(Event::new(false, EventClearMode::AutoClear));
+        let semaphore = Semaphore::new(0, 1);
+        let queue = MessageQueue::new(1);
+
+        let mut multi_wait = MultiWait::new();
+        let _quiet = multi_wait.add_event(&quiet);
+        let signaled = multi_wait.add_event(&event);
+        let acquirable = multi_wait.add_semaphore(&semaphore);
+        let not_empty = multi_wait.add_message_queue(&queue, MessageQueueWaitType::ForNotEmpty);
+        assert_eq!(multi_wait.len(), 4);
+        assert_eq!(multi_wait.try_wait_any(), None);
+        assert_eq!(multi_wait.wait_any_timeout(TimeSpan::from_millis(20)), None);
+
+        let signaler = {
+            let event = event.clone();
+            thread::spawn(move || {
+                thread::sleep(TimeSpan::from_millis(30));
+                event.signal();
+            })
+        };
+        assert_eq!(multi_wait.wait_any(), signaled);
+        signaler.join().unwrap();
+        // Firing doesn't clear the event, the caller does
+        assert_eq!(multi_wait.try_wait_any(), Some(signaled));
+        assert!(event.try_wait());
+        assert_eq!(multi_wait.try_wait_any(), None);
+
+        semaphore.release();
+        assert_eq!(multi_wait.try_wait_any(), Some(acquirable));
+        assert!(semaphore.try_acquire());
+        queue.send(7);
+        assert_eq!(multi_wait.try_wait_any(), Some(not_empty));
+        assert_eq!(queue.receive(), 7);
+    }
+
+    #[test]
+    fn multi_wait_on_thread_exit() {
+        let handle = thread::spawn(|| thread::sleep(TimeSpan::from_millis(30)));
+        {
+            let mut multi_wait = MultiWait::new();
+            let exited = multi_wait.add_thread(handle.thread());
+            assert_eq!(multi_wait.try_wait_any(), None);
+            assert_eq!(multi_wait.wait_any(), exited);
+        }
+        handle.join().unwrap();
+    }
+
+    // The only test that touches the memory heap, blocks are never given back so it would see other tests' blocks
+    #[test]
+    fn memory_heap() {
+        assert_eq!(alloc_from_heap(MEMORY_BLOCK_UNIT_SIZE).unwrap_err(), result::OUT_OF_MEMORY);
+        set_heap_size(MEMORY_HEAP_UNIT_SIZE * 2).unwrap();
+        let first = alloc_from_heap(MEMORY_BLOCK_UNIT_SIZE).unwrap() as usize;
+        assert_eq!(first % MEMORY_BLOCK_UNIT_SIZE, 0);
+        let second = alloc_from_heap(MEMORY_BLOCK_UNIT_SIZE).unwrap() as usize;
+        assert_eq!(second, first + MEMORY_BLOCK_UNIT_SIZE);
+        assert_eq!(alloc_from_heap(MEMORY_BLOCK_UNIT_SIZE).unwrap_err(), result::OUT_OF_MEMORY);
+
+        // Blocks in use can't be cut off, and the heap can't grow past the reservation
+        assert_eq!(set_heap_size(MEMORY_HEAP_UNIT_SIZE).unwrap_err(), result::BUSY);
+        assert_eq!(set_heap_size(MEMORY_HEAP_RESERVE_SIZE + MEMORY_HEAP_UNIT_SIZE).unwrap_err(), result::OUT_OF_MEMORY);
+        set_heap_size(MEMORY_HEAP_UNIT_SIZE * 3).unwrap();
+        assert_eq!(alloc_from_heap(MEMORY_BLOCK_UNIT_SIZE).unwrap() as usize, second + MEMORY_BLOCK_UNIT_SIZE);
+
+        // NnGlobalAllocator takes its block from here too. Reports don't allocate, so they're fine under its lock
+        #[cfg(feature = "mock_mem")]
+        {
+            use super::super::super::mem::{HeapReport, NnGlobalAllocator};
+
+            set_heap_size(MEMORY_HEAP_UNIT_SIZE * 4).unwrap();
+            let global = NnGlobalAllocator::new(MEMORY_BLOCK_UNIT_SIZE);
+            let block = global.with_allocator(|allocator| allocator.alloc(0x100) as usize).unwrap();
+            assert!((second + MEMORY_BLOCK_UNIT_SIZE * 2..second + MEMORY_BLOCK_UNIT_SIZE * 3).contains(&block));
+            let report = global.with_allocator(|allocator| HeapReport::capture(allocator)).unwrap();
+            assert_eq!((report.block_count, report.allocated_size), (1, 0x100));
+            let blocks = std::vec::Vec::with_capacity(1);
+            let report = global.with_allocator(|allocator| HeapReport::capture_with_blocks(allocator, blocks)).unwrap();
+            assert_eq!(report.blocks, [(block, 0x100)]);
+        }
+    }
+}
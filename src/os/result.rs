@@ -0,0 +1,10 @@
+// Known nn::os result codes, names follow the SDK's nn::os::Result* functions
+use super::super::{ErrorModule, Result};
+
+pub const MODULE: u32 = ErrorModule::Os.as_raw();
+
+pub const BUSY: Result = Result::new(MODULE, 4);
+pub const OUT_OF_MEMORY: Result = Result::new(MODULE, 8);
+pub const OUT_OF_RESOURCE: Result = Result::new(MODULE, 9);
+pub const OUT_OF_VIRTUAL_ADDRESS_SPACE: Result = Result::new(MODULE, 12);
+pub const RESOURCE_LIMIT: Result = Result::new(MODULE, 13);
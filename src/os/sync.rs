@@ -0,0 +1,13 @@
+// Blocking synchronization primitives on top of nn::os, std::sync-style
+mod condvar;
+mod event;
+mod message_queue;
+mod multi_wait;
+mod mutex;
+mod semaphore;
+pub use condvar::{Condvar, WaitTimeoutResult};
+pub use event::{Event, EventClearMode, LightEvent, SystemEvent};
+pub use message_queue::{MessageQueue, MessageQueueWaitType};
+pub use multi_wait::MultiWait;
+pub use mutex::{Mutex, MutexGuard, ReentrantMutex, ReentrantMutexGuard};
+pub use semaphore::Semaphore;
@@ -0,0 +1,90 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use super::super::super::TimeSpan;
+use super::super::{os_impl, ConditionVariableType, CONDITION_VARIABLE_STATUS_TIMEOUT};
+use super::MutexGuard;
+
+// Blocks threads until another one signals it. Waiting releases the guard's mutex and takes it back before
+// returning, and like the SDK it can wake up spuriously, so check the condition in a loop or use wait_while.
+pub struct Condvar(UnsafeCell<ConditionVariableType>);
+
+unsafe impl Send for Condvar {}
+unsafe impl Sync for Condvar {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+impl Condvar {
+    pub const fn new() -> Self {
+        Self(UnsafeCell::new(ConditionVariableType::new()))
+    }
+
+    fn as_ptr(&self) -> *mut ConditionVariableType {
+        self.0.get()
+    }
+
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        unsafe {
+            os_impl::WaitConditionVariable(self.as_ptr(), guard.mutex.raw.as_ptr());
+        }
+        guard
+    }
+
+    // Keeps waiting as long as condition returns true
+    pub fn wait_while<'a, T: ?Sized, F>(&self, mut guard: MutexGuard<'a, T>, mut condition: F) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    // The timeout isn't restarted by spurious wakeups, so a result that didn't time out doesn't mean the condition changed
+    pub fn wait_timeout<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>, timeout: TimeSpan) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let status = unsafe {
+            os_impl::TimedWaitConditionVariable(self.as_ptr(), guard.mutex.raw.as_ptr(), timeout)
+        };
+        (guard, WaitTimeoutResult(status == CONDITION_VARIABLE_STATUS_TIMEOUT))
+    }
+
+    // Wakes up one waiting thread, if there is any
+    pub fn notify_one(&self) {
+        unsafe {
+            os_impl::SignalConditionVariable(self.as_ptr())
+        }
+    }
+
+    pub fn notify_all(&self) {
+        unsafe {
+            os_impl::BroadcastConditionVariable(self.as_ptr())
+        }
+    }
+}
+
+impl Drop for Condvar {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::FinalizeConditionVariable(self.as_ptr())
+        }
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Condvar { .. }")
+    }
+}
@@ -0,0 +1,250 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::fmt;
+use super::super::super::{Result as NxResult, TimeSpan};
+use super::super::{os_impl, EventType, LightEventType, NativeHandle, SystemEventType};
+
+// Whether a successful wait resets the event. AutoClear wakes a single waiter per signal.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EventClearMode {
+    ManualClear = 0,
+    AutoClear = 1
+}
+
+// Signaled flag that threads can block on, and that can be linked to a MultiWait.
+// The SDK object can't be moved once initialized, so it lives on the heap.
+pub struct Event(Box<UnsafeCell<EventType>>);
+
+// Like Event but cheaper, for when nothing needs to wait on it through a MultiWait
+pub struct LightEvent(Box<UnsafeCell<LightEventType>>);
+
+// Event backed by a kernel object, either created here or attached to a handle from a service
+pub struct SystemEvent(Box<UnsafeCell<SystemEventType>>);
+
+unsafe impl Send for Event {}
+unsafe impl Sync for Event {}
+unsafe impl Send for LightEvent {}
+unsafe impl Sync for LightEvent {}
+unsafe impl Send for SystemEvent {}
+unsafe impl Sync for SystemEvent {}
+
+// The SDK objects are filled in by their initialize function, all that matters is that they're allocated
+unsafe fn new_uninit<T>() -> Box<UnsafeCell<T>> {
+    Box::new(core::mem::zeroed())
+}
+
+impl Event {
+    pub fn new(signaled: bool, clear_mode: EventClearMode) -> Self {
+        unsafe {
+            let event = Self(new_uninit());
+            os_impl::InitializeEvent(event.as_ptr(), signaled, clear_mode);
+            event
+        }
+    }
+
+    pub(super) fn as_ptr(&self) -> *mut EventType {
+        self.0.get()
+    }
+
+    // Wakes every waiter for ManualClear, a single one for AutoClear
+    pub fn signal(&self) {
+        unsafe {
+            os_impl::SignalEvent(self.as_ptr())
+        }
+    }
+
+    pub fn wait(&self) {
+        unsafe {
+            os_impl::WaitEvent(self.as_ptr())
+        }
+    }
+
+    // Doesn't block, returns whether the event was signaled
+    pub fn try_wait(&self) -> bool {
+        unsafe {
+            os_impl::TryWaitEvent(self.as_ptr())
+        }
+    }
+
+    // Returns false if the timeout expired before the event was signaled
+    pub fn wait_timeout(&self, timeout: TimeSpan) -> bool {
+        unsafe {
+            os_impl::TimedWaitEvent(self.as_ptr(), timeout)
+        }
+    }
+
+    pub fn clear(&self) {
+        unsafe {
+            os_impl::ClearEvent(self.as_ptr())
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::FinalizeEvent(self.as_ptr())
+        }
+    }
+}
+
+impl LightEvent {
+    pub fn new(signaled: bool, clear_mode: EventClearMode) -> Self {
+        unsafe {
+            let event = Self(new_uninit());
+            os_impl::InitializeLightEvent(event.as_ptr(), signaled, clear_mode);
+            event
+        }
+    }
+
+    fn as_ptr(&self) -> *mut LightEventType {
+        self.0.get()
+    }
+
+    pub fn signal(&self) {
+        unsafe {
+            os_impl::SignalLightEvent(self.as_ptr())
+        }
+    }
+
+    pub fn wait(&self) {
+        unsafe {
+            os_impl::WaitLightEvent(self.as_ptr())
+        }
+    }
+
+    pub fn try_wait(&self) -> bool {
+        unsafe {
+            os_impl::TryWaitLightEvent(self.as_ptr())
+        }
+    }
+
+    pub fn wait_timeout(&self, timeout: TimeSpan) -> bool {
+        unsafe {
+            os_impl::TimedWaitLightEvent(self.as_ptr(), timeout)
+        }
+    }
+
+    pub fn clear(&self) {
+        unsafe {
+            os_impl::ClearLightEvent(self.as_ptr())
+        }
+    }
+}
+
+impl Drop for LightEvent {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::FinalizeLightEvent(self.as_ptr())
+        }
+    }
+}
+
+impl SystemEvent {
+    // An interprocess event gets a readable handle that can be passed to other processes
+    pub fn new(clear_mode: EventClearMode, interprocess: bool) -> Result<Self, NxResult> {
+        unsafe {
+            let event = Self(new_uninit());
+            let result = os_impl::CreateSystemEvent(event.as_ptr(), clear_mode, interprocess);
+            if result.is_success() {
+                Ok(event)
+            } else {
+                // Nothing was created, so DestroySystemEvent mustn't run
+                core::mem::forget(event);
+                Err(result)
+            }
+        }
+    }
+
+    // Wraps the readable handle of an event signaled by someone else, typically returned by a service.
+    // A managed handle is closed when the SystemEvent is dropped.
+    /// # Safety
+    /// `handle` has to refer to a readable event, and nothing else may close it if it's managed.
+    pub unsafe fn from_readable_handle(handle: NativeHandle, is_managed: bool, clear_mode: EventClearMode) -> Self {
+        let event = Self(new_uninit());
+        os_impl::AttachReadableHandleToSystemEvent(event.as_ptr(), handle, is_managed, clear_mode);
+        event
+    }
+
+    /// # Safety
+    /// Same as `from_readable_handle`, and `writable_handle` has to belong to the same event.
+    pub unsafe fn from_handles(readable_handle: NativeHandle, is_readable_managed: bool, writable_handle: NativeHandle, is_writable_managed: bool, clear_mode: EventClearMode) -> Self {
+        let event = Self(new_uninit());
+        os_impl::AttachSystemEvent(event.as_ptr(), readable_handle, is_readable_managed, writable_handle, is_writable_managed, clear_mode);
+        event
+    }
+
+    pub(super) fn as_ptr(&self) -> *mut SystemEventType {
+        self.0.get()
+    }
+
+    // Needs a writable handle, events attached with from_readable_handle can only be waited on
+    pub fn signal(&self) {
+        unsafe {
+            os_impl::SignalSystemEvent(self.as_ptr())
+        }
+    }
+
+    pub fn wait(&self) {
+        unsafe {
+            os_impl::WaitSystemEvent(self.as_ptr())
+        }
+    }
+
+    pub fn try_wait(&self) -> bool {
+        unsafe {
+            os_impl::TryWaitSystemEvent(self.as_ptr())
+        }
+    }
+
+    pub fn wait_timeout(&self, timeout: TimeSpan) -> bool {
+        unsafe {
+            os_impl::TimedWaitSystemEvent(self.as_ptr(), timeout)
+        }
+    }
+
+    pub fn clear(&self) {
+        unsafe {
+            os_impl::ClearSystemEvent(self.as_ptr())
+        }
+    }
+
+    pub fn readable_handle(&self) -> NativeHandle {
+        unsafe {
+            os_impl::GetReadableHandleOfSystemEvent(self.as_ptr())
+        }
+    }
+
+    pub fn writable_handle(&self) -> NativeHandle {
+        unsafe {
+            os_impl::GetWritableHandleOfSystemEvent(self.as_ptr())
+        }
+    }
+}
+
+impl Drop for SystemEvent {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::DestroySystemEvent(self.as_ptr())
+        }
+    }
+}
+
+impl fmt::Debug for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Event({:p})", self.as_ptr())
+    }
+}
+
+impl fmt::Debug for LightEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LightEvent({:p})", self.as_ptr())
+    }
+}
+
+impl fmt::Debug for SystemEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SystemEvent({:p})", self.as_ptr())
+    }
+}
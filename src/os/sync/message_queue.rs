@@ -0,0 +1,115 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+use super::super::super::TimeSpan;
+use super::super::{os_impl, MessageQueueType};
+
+// What a MultiWait linked to a MessageQueue waits for
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MessageQueueWaitType {
+    ForNotFull = 1,
+    ForNotEmpty = 2
+}
+
+// Fixed capacity FIFO of usize messages, usually pointers or indices
+pub struct MessageQueue {
+    raw: Box<UnsafeCell<MessageQueueType>>,
+    // Written by the SDK through the pointer it was given, so it isn't kept as a Box
+    buffer: *mut [usize]
+}
+
+unsafe impl Send for MessageQueue {}
+unsafe impl Sync for MessageQueue {}
+
+impl MessageQueue {
+    // Panics if capacity is 0
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("message queue capacity can't be 0");
+        }
+        unsafe {
+            let queue = Self {
+                raw: Box::new(core::mem::zeroed()),
+                buffer: Box::into_raw(vec![0usize; capacity].into_boxed_slice())
+            };
+            os_impl::InitializeMessageQueue(queue.as_ptr(), queue.buffer as *mut usize, capacity);
+            queue
+        }
+    }
+
+    pub(super) fn as_ptr(&self) -> *mut MessageQueueType {
+        self.raw.get()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // Blocks while the queue is full
+    pub fn send(&self, data: usize) {
+        unsafe {
+            os_impl::SendMessageQueue(self.as_ptr(), data)
+        }
+    }
+
+    pub fn try_send(&self, data: usize) -> bool {
+        unsafe {
+            os_impl::TrySendMessageQueue(self.as_ptr(), data)
+        }
+    }
+
+    // Returns false if the queue stayed full until the timeout expired
+    pub fn send_timeout(&self, data: usize, timeout: TimeSpan) -> bool {
+        unsafe {
+            os_impl::TimedSendMessageQueue(self.as_ptr(), data, timeout)
+        }
+    }
+
+    // Blocks while the queue is empty
+    pub fn receive(&self) -> usize {
+        unsafe {
+            let mut data = 0;
+            os_impl::ReceiveMessageQueue(&mut data, self.as_ptr());
+            data
+        }
+    }
+
+    pub fn try_receive(&self) -> Option<usize> {
+        unsafe {
+            let mut data = 0;
+            if os_impl::TryReceiveMessageQueue(&mut data, self.as_ptr()) {
+                Some(data)
+            } else {
+                None
+            }
+        }
+    }
+
+    pub fn receive_timeout(&self, timeout: TimeSpan) -> Option<usize> {
+        unsafe {
+            let mut data = 0;
+            if os_impl::TimedReceiveMessageQueue(&mut data, self.as_ptr(), timeout) {
+                Some(data)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl Drop for MessageQueue {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::FinalizeMessageQueue(self.as_ptr());
+            drop(Box::from_raw(self.buffer));
+        }
+    }
+}
+
+impl fmt::Debug for MessageQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MessageQueue").field("capacity", &self.capacity()).finish()
+    }
+}
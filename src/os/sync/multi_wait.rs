@@ -0,0 +1,148 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use super::super::super::TimeSpan;
+use super::super::{os_impl, MultiWaitHolderType, MultiWaitType, ThreadHandle};
+use super::{Event, MessageQueue, MessageQueueWaitType, Semaphore, SystemEvent};
+
+// Waits on several objects at once and says which one fired, by the index its add_ method returned.
+// Nothing is consumed by the wait: an auto clear event stays signaled and a semaphore keeps its count,
+// so follow up with try_wait, try_acquire or try_receive on the object that fired.
+//
+//     let mut multi_wait = MultiWait::new();
+//     let jobs = multi_wait.add_semaphore(&job_semaphore);
+//     let quit = multi_wait.add_event(&quit_event);
+//     match multi_wait.wait_any() {
+//         index if index == jobs => ...,
+//         _ => ...
+//     }
+pub struct MultiWait<'a> {
+    raw: Box<UnsafeCell<MultiWaitType>>,
+    // Linked into raw, the SDK keeps pointers to them so they're boxed as well. A plain Vec of holders would
+    // move them whenever it grows and leave the SDK's list pointing at the old copies.
+    #[allow(clippy::vec_box)]
+    holders: Vec<Box<UnsafeCell<MultiWaitHolderType>>>,
+    // Also keeps MultiWait from being Send or Sync, like the &ThreadHandle add_thread takes
+    objects: PhantomData<&'a ThreadHandle>
+}
+
+impl<'a> MultiWait<'a> {
+    pub fn new() -> Self {
+        unsafe {
+            let multi_wait = Self {
+                raw: Box::new(core::mem::zeroed()),
+                holders: Vec::new(),
+                objects: PhantomData
+            };
+            os_impl::InitializeMultiWait(multi_wait.as_ptr());
+            multi_wait
+        }
+    }
+
+    fn as_ptr(&self) -> *mut MultiWaitType {
+        self.raw.get()
+    }
+
+    // Links a holder set up by init and tags it with its index
+    fn add<F: FnOnce(*mut MultiWaitHolderType)>(&mut self, init: F) -> usize {
+        let index = self.holders.len();
+        let holder: Box<UnsafeCell<MultiWaitHolderType>> = Box::new(unsafe { core::mem::zeroed() });
+        init(holder.get());
+        unsafe {
+            os_impl::SetMultiWaitHolderUserData(holder.get(), index);
+            os_impl::LinkMultiWaitHolder(self.as_ptr(), holder.get());
+        }
+        self.holders.push(holder);
+        index
+    }
+
+    // Fires while the event is signaled
+    pub fn add_event(&mut self, event: &'a Event) -> usize {
+        self.add(|holder| unsafe { os_impl::InitializeMultiWaitHolderEvent(holder, event.as_ptr()) })
+    }
+
+    pub fn add_system_event(&mut self, event: &'a SystemEvent) -> usize {
+        self.add(|holder| unsafe { os_impl::InitializeMultiWaitHolderSystemEvent(holder, event.as_ptr()) })
+    }
+
+    // Fires once the thread has exited
+    pub fn add_thread(&mut self, thread: &'a ThreadHandle) -> usize {
+        self.add(|holder| unsafe { os_impl::InitializeMultiWaitHolderThread(holder, thread.as_ptr()) })
+    }
+
+    // Fires while the count is above 0
+    pub fn add_semaphore(&mut self, semaphore: &'a Semaphore) -> usize {
+        self.add(|holder| unsafe { os_impl::InitializeMultiWaitHolderSemaphore(holder, semaphore.as_ptr()) })
+    }
+
+    pub fn add_message_queue(&mut self, queue: &'a MessageQueue, wait_type: MessageQueueWaitType) -> usize {
+        self.add(|holder| unsafe { os_impl::InitializeMultiWaitHolderMessageQueue(holder, queue.as_ptr(), wait_type) })
+    }
+
+    pub fn len(&self) -> usize {
+        self.holders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.holders.is_empty()
+    }
+
+    fn index_of(&self, holder: *mut MultiWaitHolderType) -> Option<usize> {
+        if holder.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(os_impl::GetMultiWaitHolderUserData(holder))
+            }
+        }
+    }
+
+    // Blocks until one of the objects fires. Waiting with nothing added would never return, so that panics.
+    pub fn wait_any(&self) -> usize {
+        if self.is_empty() {
+            panic!("MultiWait::wait_any called without anything to wait on");
+        }
+        unsafe {
+            os_impl::GetMultiWaitHolderUserData(os_impl::WaitAny(self.as_ptr()))
+        }
+    }
+
+    pub fn try_wait_any(&self) -> Option<usize> {
+        unsafe {
+            self.index_of(os_impl::TryWaitAny(self.as_ptr()))
+        }
+    }
+
+    // None if nothing fired before the timeout expired
+    pub fn wait_any_timeout(&self, timeout: TimeSpan) -> Option<usize> {
+        unsafe {
+            self.index_of(os_impl::TimedWaitAny(self.as_ptr(), timeout))
+        }
+    }
+}
+
+impl Drop for MultiWait<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::UnlinkAllMultiWaitHolder(self.as_ptr());
+            for holder in self.holders.iter() {
+                os_impl::FinalizeMultiWaitHolder(holder.get());
+            }
+            os_impl::FinalizeMultiWait(self.as_ptr());
+        }
+    }
+}
+
+impl Default for MultiWait<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MultiWait<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MultiWait").field("len", &self.len()).finish()
+    }
+}
@@ -0,0 +1,298 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use super::super::{os_impl, MutexType};
+
+// Owns a MutexType. The SDK object doesn't point into itself, so it can be moved while unlocked.
+pub(super) struct RawMutex(UnsafeCell<MutexType>);
+
+unsafe impl Send for RawMutex {}
+unsafe impl Sync for RawMutex {}
+
+impl RawMutex {
+    const fn new(is_recursive: bool) -> Self {
+        Self(UnsafeCell::new(MutexType::new(is_recursive)))
+    }
+
+    fn with_lock_level(is_recursive: bool, lock_level: i32) -> Self {
+        assert!((1..=31).contains(&lock_level), "lock level {} is out of range 1..=31", lock_level);
+        let raw = Self::new(is_recursive);
+        unsafe {
+            os_impl::InitializeMutex(raw.as_ptr(), is_recursive, lock_level);
+        }
+        raw
+    }
+
+    pub(super) fn as_ptr(&self) -> *mut MutexType {
+        self.0.get()
+    }
+
+    fn lock(&self) {
+        unsafe {
+            os_impl::LockMutex(self.as_ptr())
+        }
+    }
+
+    fn try_lock(&self) -> bool {
+        unsafe {
+            os_impl::TryLockMutex(self.as_ptr())
+        }
+    }
+
+    // Only valid from the thread holding the lock
+    unsafe fn unlock(&self) {
+        os_impl::UnlockMutex(self.as_ptr())
+    }
+
+    fn is_locked_by_current_thread(&self) -> bool {
+        unsafe {
+            os_impl::IsMutexLockedByCurrentThread(self.as_ptr())
+        }
+    }
+}
+
+impl Drop for RawMutex {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::FinalizeMutex(self.as_ptr())
+        }
+    }
+}
+
+// Blocks instead of spinning, and can be created in a const so it works in statics.
+// Locking it again from the thread that holds it is a deadlock the SDK aborts on, use ReentrantMutex for that.
+pub struct Mutex<T: ?Sized> {
+    pub(super) raw: RawMutex,
+    data: UnsafeCell<T>
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+// Unlocks on drop. The SDK requires the unlocking thread to be the one that locked, so guards can't be sent.
+pub struct MutexGuard<'a, T: ?Sized> {
+    pub(super) mutex: &'a Mutex<T>,
+    _not_send: PhantomData<*const ()>
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+// Can be locked several times by the same thread, so the guard only hands out shared references
+pub struct ReentrantMutex<T: ?Sized> {
+    raw: RawMutex,
+    data: T
+}
+
+unsafe impl<T: ?Sized + Send> Send for ReentrantMutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for ReentrantMutex<T> {}
+
+pub struct ReentrantMutexGuard<'a, T: ?Sized> {
+    mutex: &'a ReentrantMutex<T>,
+    _not_send: PhantomData<*const ()>
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for ReentrantMutexGuard<'_, T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            raw: RawMutex::new(false),
+            data: UnsafeCell::new(value)
+        }
+    }
+
+    // Lock levels go from 1 to 31, 0 is reserved for mutexes without one like those made by new. Debug builds
+    // of the SDK abort if a thread locks a mutex with a level lower than or equal to one it already holds.
+    pub fn with_lock_level(value: T, lock_level: i32) -> Self {
+        Self {
+            raw: RawMutex::with_lock_level(false, lock_level),
+            data: UnsafeCell::new(value)
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.raw.lock();
+        MutexGuard::new(self)
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if self.raw.try_lock() {
+            Some(MutexGuard::new(self))
+        } else {
+            None
+        }
+    }
+
+    pub fn is_locked_by_current_thread(&self) -> bool {
+        self.raw.is_locked_by_current_thread()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    fn new(mutex: &'a Mutex<T>) -> Self {
+        Self {
+            mutex,
+            _not_send: PhantomData
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe {
+            &*self.mutex.data.get()
+        }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {
+            &mut *self.mutex.data.get()
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            self.mutex.raw.unlock()
+        }
+    }
+}
+
+impl<T> ReentrantMutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            raw: RawMutex::new(true),
+            data: value
+        }
+    }
+
+    // Same lock levels as Mutex::with_lock_level
+    pub fn with_lock_level(value: T, lock_level: i32) -> Self {
+        Self {
+            raw: RawMutex::with_lock_level(true, lock_level),
+            data: value
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: ?Sized> ReentrantMutex<T> {
+    pub fn lock(&self) -> ReentrantMutexGuard<'_, T> {
+        self.raw.lock();
+        ReentrantMutexGuard::new(self)
+    }
+
+    pub fn try_lock(&self) -> Option<ReentrantMutexGuard<'_, T>> {
+        if self.raw.try_lock() {
+            Some(ReentrantMutexGuard::new(self))
+        } else {
+            None
+        }
+    }
+
+    pub fn is_locked_by_current_thread(&self) -> bool {
+        self.raw.is_locked_by_current_thread()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+}
+
+impl<'a, T: ?Sized> ReentrantMutexGuard<'a, T> {
+    fn new(mutex: &'a ReentrantMutex<T>) -> Self {
+        Self {
+            mutex,
+            _not_send: PhantomData
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for ReentrantMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.mutex.data
+    }
+}
+
+impl<T: ?Sized> Drop for ReentrantMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            self.mutex.raw.unlock()
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Default> Default for ReentrantMutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut d = f.debug_struct("Mutex");
+        // Trying to lock it again from the thread that holds it would abort
+        let guard = if self.is_locked_by_current_thread() { None } else { self.try_lock() };
+        match guard {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>"))
+        };
+        d.finish()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ReentrantMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut d = f.debug_struct("ReentrantMutex");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>"))
+        };
+        d.finish()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ReentrantMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,85 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::fmt;
+use super::super::super::TimeSpan;
+use super::super::{os_impl, SemaphoreType};
+
+// Counting semaphore, can also be linked to a MultiWait which fires while the count is above 0.
+// The SDK object can't be moved once initialized, so it lives on the heap.
+pub struct Semaphore(Box<UnsafeCell<SemaphoreType>>);
+
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+
+impl Semaphore {
+    // Panics unless 0 <= initial_count <= max_count and max_count >= 1, the SDK would abort instead
+    pub fn new(initial_count: i32, max_count: i32) -> Self {
+        if max_count < 1 || initial_count < 0 || initial_count > max_count {
+            panic!("invalid semaphore counts: {} out of {}", initial_count, max_count);
+        }
+        unsafe {
+            let semaphore = Self(Box::new(core::mem::zeroed()));
+            os_impl::InitializeSemaphore(semaphore.as_ptr(), initial_count, max_count);
+            semaphore
+        }
+    }
+
+    pub(super) fn as_ptr(&self) -> *mut SemaphoreType {
+        self.0.get()
+    }
+
+    // Blocks until the count is above 0, then decrements it
+    pub fn acquire(&self) {
+        unsafe {
+            os_impl::AcquireSemaphore(self.as_ptr())
+        }
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        unsafe {
+            os_impl::TryAcquireSemaphore(self.as_ptr())
+        }
+    }
+
+    // Returns false if the timeout expired before the count could be decremented
+    pub fn acquire_timeout(&self, timeout: TimeSpan) -> bool {
+        unsafe {
+            os_impl::TimedAcquireSemaphore(self.as_ptr(), timeout)
+        }
+    }
+
+    // Going over the max count aborts
+    pub fn release(&self) {
+        unsafe {
+            os_impl::ReleaseSemaphore(self.as_ptr())
+        }
+    }
+
+    // count has to be at least 1 and fit under the max count along with the current count, the SDK aborts
+    // otherwise. That can't be checked here without racing other releases, so it's up to the caller.
+    pub fn release_count(&self, count: i32) {
+        unsafe {
+            os_impl::ReleaseSemaphoreCount(self.as_ptr(), count)
+        }
+    }
+
+    pub fn count(&self) -> i32 {
+        unsafe {
+            os_impl::GetCurrentSemaphoreCount(self.as_ptr())
+        }
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe {
+            os_impl::FinalizeSemaphore(self.as_ptr())
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore").field("count", &self.count()).finish()
+    }
+}
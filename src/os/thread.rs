@@ -0,0 +1,292 @@
+// std::thread-style spawning on top of nn::os threads: closures instead of extern "C" entrypoints,
+// stacks allocated and freed for you, and return values delivered through JoinHandle::join
+use alloc::alloc::{alloc, dealloc, Layout};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use libc::*;
+use super::super::{Result as NxResult, TimeSpan};
+use super::{Thread, ThreadHandle};
+
+mod scoped;
+pub use scoped::{scope, Scope, ScopedJoinHandle};
+
+// nn::os requires thread stacks to be page aligned and a whole number of pages
+const STACK_ALIGNMENT: usize = 0x1000;
+pub const DEFAULT_STACK_SIZE: usize = 0x10000;
+pub const CORE_COUNT: i32 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpawnError {
+    InvalidPriority(i32),
+    InvalidCore(i32),
+    InvalidStackSize(usize),
+    // The stack or the ThreadType couldn't be allocated
+    OutOfMemory,
+    // CreateThread itself failed
+    Create(NxResult)
+}
+
+// The thread didn't run to completion, so there's no return value
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct JoinError;
+
+#[derive(Debug, Clone)]
+pub struct Builder {
+    name: Option<String>,
+    priority: i32,
+    core: Option<i32>,
+    stack_size: usize
+}
+
+// Where the thread leaves its return value, read by the JoinHandle once WaitThread returns
+struct Packet<T> {
+    result: UnsafeCell<Option<T>>,
+    finished: AtomicBool
+}
+
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+struct Start<F, T> {
+    main: F,
+    packet: Arc<Packet<T>>
+}
+
+// A started thread and the stack it runs on, both freed once it has been waited on
+struct Native {
+    thread: Thread,
+    stack: *mut u8,
+    stack_layout: Layout
+}
+
+// The stack stays put until join, so the thread can be joined from anywhere. Not Sync, the thread's
+// ThreadHandle can be changed through a shared reference.
+unsafe impl Send for Native {}
+
+// Dropping the handle waits for the thread like join does, so its stack and ThreadType are always freed.
+// Use detach to let the thread run on without anyone waiting for it.
+pub struct JoinHandle<T> {
+    // Only None once joined or detached
+    native: Option<Native>,
+    packet: Arc<Packet<T>>
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            priority: Thread::PRIORITY_DEFAULT,
+            core: None,
+            stack_size: DEFAULT_STACK_SIZE
+        }
+    }
+
+    // Long names are kept on the heap and freed once the thread is joined
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    // Lower values run first, must be between Thread::PRIORITY_MAX and Thread::PRIORITY_MIN
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    // Ideal core for the thread, the process' default core is used if this isn't set
+    pub fn core(mut self, core: i32) -> Self {
+        self.core = Some(core);
+        self
+    }
+
+    // Rounded up to a whole number of pages
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    pub fn spawn<F, T>(self, main: F) -> Result<JoinHandle<T>, SpawnError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static
+    {
+        let (native, packet) = unsafe { self.spawn_unchecked(main)? };
+        Ok(JoinHandle {
+            native: Some(native),
+            packet
+        })
+    }
+
+    // The caller has to join the thread before anything main borrows goes away
+    unsafe fn spawn_unchecked<F, T>(self, main: F) -> Result<(Native, Arc<Packet<T>>), SpawnError>
+    where
+        F: FnOnce() -> T + Send,
+        T: Send
+    {
+        if !(Thread::PRIORITY_MAX..=Thread::PRIORITY_MIN).contains(&self.priority) {
+            return Err(SpawnError::InvalidPriority(self.priority));
+        }
+        if let Some(core) = self.core {
+            if !(0..CORE_COUNT).contains(&core) {
+                return Err(SpawnError::InvalidCore(core));
+            }
+        }
+        let stack_size = match self.stack_size.checked_add(STACK_ALIGNMENT - 1) {
+            Some(size) if self.stack_size != 0 => size & !(STACK_ALIGNMENT - 1),
+            _ => return Err(SpawnError::InvalidStackSize(self.stack_size))
+        };
+        let stack_layout = Layout::from_size_align(stack_size, STACK_ALIGNMENT).map_err(|_| SpawnError::InvalidStackSize(self.stack_size))?;
+
+        let stack = alloc(stack_layout);
+        if stack.is_null() {
+            return Err(SpawnError::OutOfMemory);
+        }
+        let packet = Arc::new(Packet {
+            result: UnsafeCell::new(None),
+            finished: AtomicBool::new(false)
+        });
+        let start = Box::into_raw(Box::new(Start {
+            main,
+            packet: packet.clone()
+        }));
+        let result = match self.core {
+            Some(core) => Thread::new_on_core(thread_start::<F, T>, start as _, stack as _, stack_size, self.priority, core),
+            None => Thread::new(thread_start::<F, T>, start as _, stack as _, stack_size, self.priority)
+        };
+        let mut thread = match result {
+            Ok(thread) => thread,
+            Err(result) => {
+                drop(Box::from_raw(start));
+                dealloc(stack, stack_layout);
+                return Err(if result == super::result::OUT_OF_MEMORY { SpawnError::OutOfMemory } else { SpawnError::Create(result) });
+            }
+        };
+
+        if let Some(name) = &self.name {
+            thread.set_name(name);
+        }
+        thread.start();
+        Ok((Native {
+            thread,
+            stack,
+            stack_layout
+        }, packet))
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+extern "C" fn thread_start<F: FnOnce() -> T, T>(arg: *mut c_void) {
+    let start = unsafe { Box::from_raw(arg as *mut Start<F, T>) };
+    let Start { main, packet } = *start;
+
+    // Without std a panic can't be caught, the result just never gets filled in
+    #[cfg(feature = "mock_os")]
+    let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(main)).ok();
+    #[cfg(not(feature = "mock_os"))]
+    let result = Some(main());
+
+    unsafe {
+        *packet.result.get() = result;
+    }
+    packet.finished.store(true, Ordering::Release);
+}
+
+impl Native {
+    fn join(mut self) {
+        self.thread.wait();
+        drop(self.thread);
+        unsafe {
+            dealloc(self.stack, self.stack_layout);
+        }
+    }
+}
+
+impl<T> Packet<T> {
+    // Only valid once the thread has exited
+    unsafe fn take(&self) -> Result<T, JoinError> {
+        (*self.result.get()).take().ok_or(JoinError)
+    }
+}
+
+impl<T> JoinHandle<T> {
+    // Whether the closure has returned, join won't block once this is true
+    pub fn is_finished(&self) -> bool {
+        self.packet.finished.load(Ordering::Acquire)
+    }
+
+    pub fn thread(&self) -> &ThreadHandle {
+        match &self.native {
+            Some(native) => &native.thread,
+            None => unreachable!()
+        }
+    }
+
+    // Waits for the thread to exit, then frees its stack and ThreadType
+    pub fn join(mut self) -> Result<T, JoinError> {
+        if let Some(native) = self.native.take() {
+            native.join();
+        }
+        unsafe {
+            self.packet.take()
+        }
+    }
+
+    // Lets the thread run on its own. Nothing knows when it exits, so its stack and ThreadType are leaked,
+    // keep this to threads that live as long as the process.
+    pub fn detach(mut self) {
+        core::mem::forget(self.native.take());
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if let Some(native) = self.native.take() {
+            native.join();
+        }
+    }
+}
+
+pub fn spawn<F, T>(main: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static
+{
+    match Builder::new().spawn(main) {
+        Ok(handle) => handle,
+        Err(e) => panic!("failed to spawn thread: {}", e)
+    }
+}
+
+pub fn sleep(time: TimeSpan) {
+    Thread::sleep(time)
+}
+
+pub fn yield_now() {
+    Thread::yield_now()
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnError::InvalidPriority(priority) => write!(f, "priority {} is outside {}..={}", priority, Thread::PRIORITY_MAX, Thread::PRIORITY_MIN),
+            SpawnError::InvalidCore(core) => write!(f, "core {} is outside 0..{}", core, CORE_COUNT),
+            SpawnError::InvalidStackSize(size) => write!(f, "invalid stack size {:#x}", size),
+            SpawnError::OutOfMemory => f.write_str("out of memory"),
+            SpawnError::Create(result) => write!(f, "CreateThread failed with {}", result)
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("thread panicked")
+    }
+}
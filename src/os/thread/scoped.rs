@@ -0,0 +1,145 @@
+// Threads that can borrow from the stack of whoever spawned them, see scope
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use super::super::super::spin::SpinLock;
+use super::{Builder, JoinError, Native, Packet, SpawnError};
+
+pub struct Scope<'scope, 'env: 'scope> {
+    data: ScopeData,
+    // Invariant in both lifetimes, same as std::thread::Scope
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>
+}
+
+struct ScopeData {
+    // Threads that haven't been joined through their ScopedJoinHandle yet
+    threads: SpinLock<Vec<(usize, Native)>>,
+    next_id: AtomicUsize,
+    // Threads that panicked and haven't had their JoinError picked up by a join
+    unhandled_panics: AtomicUsize
+}
+
+pub struct ScopedJoinHandle<'scope, T> {
+    id: usize,
+    packet: Arc<Packet<T>>,
+    scope: &'scope ScopeData
+}
+
+// Runs all the threads that need joining, also while unwinding out of the scope closure
+struct JoinAll<'a>(&'a ScopeData);
+
+// Only dropped if the thread's closure unwinds
+struct PanicFlag<'a>(&'a ScopeData);
+
+// Spawned threads can borrow anything that outlives the scope, and all of them (including ones spawned
+// by other scoped threads) are joined before it returns. If a thread panicked and its handle wasn't
+// joined, scope panics once everything has been joined.
+pub fn scope<'env, F, T>(f: F) -> T
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T
+{
+    let scope = Scope {
+        data: ScopeData {
+            threads: SpinLock::new(Vec::new()),
+            next_id: AtomicUsize::new(0),
+            unhandled_panics: AtomicUsize::new(0)
+        },
+        scope: PhantomData,
+        env: PhantomData
+    };
+    let result = {
+        let _join = JoinAll(&scope.data);
+        f(&scope)
+    };
+    if scope.data.unhandled_panics.load(Ordering::Acquire) != 0 {
+        panic!("a scoped thread panicked");
+    }
+    result
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    // Panics if the thread can't be created, use Builder::spawn_scoped to handle that
+    pub fn spawn<F, T>(&'scope self, main: F) -> ScopedJoinHandle<'scope, T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope
+    {
+        match Builder::new().spawn_scoped(self, main) {
+            Ok(handle) => handle,
+            Err(e) => panic!("failed to spawn thread: {}", e)
+        }
+    }
+}
+
+impl Builder {
+    pub fn spawn_scoped<'scope, 'env, F, T>(self, scope: &'scope Scope<'scope, 'env>, main: F) -> Result<ScopedJoinHandle<'scope, T>, SpawnError>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope
+    {
+        let data = &scope.data;
+        let main = move || {
+            let flag = PanicFlag(data);
+            let result = main();
+            core::mem::forget(flag);
+            result
+        };
+        // The thread is pushed before this returns, so scope always gets to join it
+        let (native, packet) = unsafe { self.spawn_unchecked(main)? };
+        let id = data.next_id.fetch_add(1, Ordering::Relaxed);
+        data.threads.lock().push((id, native));
+        Ok(ScopedJoinHandle {
+            id,
+            packet,
+            scope: data
+        })
+    }
+}
+
+impl<'scope, T> ScopedJoinHandle<'scope, T> {
+    pub fn is_finished(&self) -> bool {
+        self.packet.finished.load(Ordering::Acquire)
+    }
+
+    pub fn join(self) -> Result<T, JoinError> {
+        let native = {
+            let mut threads = self.scope.threads.lock();
+            threads.iter().position(|(id, _)| *id == self.id).map(|index| threads.swap_remove(index).1)
+        };
+        match native {
+            Some(native) => native.join(),
+            // The scope is already joining it, which frees the thread but leaves the result alone
+            None => {
+                while !self.is_finished() {
+                    super::yield_now();
+                }
+            }
+        }
+        let result = unsafe { self.packet.take() };
+        if result.is_err() {
+            self.scope.unhandled_panics.fetch_sub(1, Ordering::Release);
+        }
+        result
+    }
+}
+
+impl Drop for JoinAll<'_> {
+    fn drop(&mut self) {
+        // Joined threads may have spawned more, so keep going until none are left
+        loop {
+            let native = self.0.threads.lock().pop();
+            match native {
+                Some((_, native)) => native.join(),
+                None => break
+            }
+        }
+    }
+}
+
+impl Drop for PanicFlag<'_> {
+    fn drop(&mut self) {
+        self.0.unhandled_panics.fetch_add(1, Ordering::Release);
+    }
+}
@@ -0,0 +1,191 @@
+// Thread local storage on top of nn::os TLS slots, plus a thread_local!-style macro for statics
+use alloc::boxed::Box;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use super::super::{get_rust_result, Result as NxResult};
+use super::os_impl;
+
+// Called with the slot's value when a thread created through nn::os exits, if the value isn't 0
+pub type TlsDestructor = extern "C" fn(usize);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TlsSlot(u32);
+
+impl TlsSlot {
+    pub(super) const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    pub const fn as_raw(&self) -> u32 {
+        self.0
+    }
+
+    // Fails with os::result::OUT_OF_RESOURCE once every slot is in use
+    #[dev_inline]
+    pub fn allocate(destructor: Option<TlsDestructor>) -> Result<Self, NxResult> {
+        unsafe {
+            let mut slot = Self(0);
+            let result = os_impl::AllocateTlsSlot(&mut slot, destructor);
+            get_rust_result!(result, slot)
+        }
+    }
+
+    // Values still set on other threads are dropped without running the destructor
+    #[dev_inline]
+    pub fn free(self) {
+        unsafe {
+            os_impl::FreeTlsSlot(self)
+        }
+    }
+
+    #[dev_inline]
+    pub fn get(&self) -> usize {
+        unsafe {
+            os_impl::GetTlsValue(*self)
+        }
+    }
+
+    #[dev_inline]
+    pub fn set(&self, value: usize) {
+        unsafe {
+            os_impl::SetTlsValue(*self, value)
+        }
+    }
+}
+
+// Stored in the slot while the value's destructor runs and afterwards, so it can't be initialized again
+const DESTROYED: usize = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    // The value was already dropped because the thread is exiting
+    Destroyed,
+    // No TLS slot could be allocated for the key
+    NoSlot(NxResult)
+}
+
+// Key for a per-thread value created with nn_thread_local!. Each thread lazily gets its own value from the
+// init function on first access, and it's dropped when the thread exits. Threads that weren't created
+// through nn::os, like the main thread, never run TLS destructors so their values are leaked instead.
+pub struct LocalKey<T: 'static> {
+    // Allocated on first access, stores the slot plus one so 0 means there's no slot yet
+    slot: AtomicUsize,
+    init: fn() -> T
+}
+
+struct Value<T> {
+    value: T,
+    slot: TlsSlot
+}
+
+extern "C" fn destroy_value<T>(value: usize) {
+    // The sentinel is meant to outlive the destructor, so a later destructor pass may hand it back here
+    if value == DESTROYED {
+        return;
+    }
+    unsafe {
+        let value = Box::from_raw(value as *mut Value<T>);
+        value.slot.set(DESTROYED);
+        drop(value);
+    }
+}
+
+impl<T: 'static> LocalKey<T> {
+    #[doc(hidden)]
+    pub const fn new(init: fn() -> T) -> Self {
+        Self {
+            slot: AtomicUsize::new(0),
+            init
+        }
+    }
+
+    fn slot(&'static self) -> Result<TlsSlot, AccessError> {
+        match self.slot.load(Ordering::Acquire) {
+            0 => {},
+            slot => return Ok(TlsSlot((slot - 1) as u32))
+        }
+        let slot = TlsSlot::allocate(Some(destroy_value::<T>)).map_err(AccessError::NoSlot)?;
+        match self.slot.compare_exchange(0, slot.0 as usize + 1, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => Ok(slot),
+            // Another thread allocated one first
+            Err(winner) => {
+                slot.free();
+                Ok(TlsSlot((winner - 1) as u32))
+            }
+        }
+    }
+
+    // Panics if the value was already destroyed or no slot is left for the key
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R
+    {
+        match self.try_with(f) {
+            Ok(result) => result,
+            Err(e) => panic!("cannot access a TLS value: {}", e)
+        }
+    }
+
+    pub fn try_with<F, R>(&'static self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&T) -> R
+    {
+        let slot = self.slot()?;
+        let value = match slot.get() {
+            DESTROYED => return Err(AccessError::Destroyed),
+            0 => {
+                let value = (self.init)();
+                // init may have accessed the key itself, keep whichever value got there first
+                match slot.get() {
+                    DESTROYED => return Err(AccessError::Destroyed),
+                    0 => {
+                        let value = Box::into_raw(Box::new(Value { value, slot })) as usize;
+                        slot.set(value);
+                        value
+                    },
+                    existing => existing
+                }
+            },
+            value => value
+        };
+        unsafe {
+            Ok(f(&(*(value as *const Value<T>)).value))
+        }
+    }
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessError::Destroyed => f.write_str("the value was destroyed when its thread exited"),
+            AccessError::NoSlot(result) => write!(f, "AllocateTlsSlot failed with {}", result)
+        }
+    }
+}
+
+// Declares statics of type LocalKey<T>, each thread gets its own value initialized from the expression:
+//
+//     nn_thread_local! {
+//         static COUNTER: Cell<u32> = Cell::new(0);
+//     }
+//
+//     COUNTER.with(|counter| counter.set(counter.get() + 1));
+#[macro_export]
+macro_rules! nn_thread_local {
+    () => {};
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr; $($rest:tt)*) => {
+        $crate::nn_thread_local!($(#[$attr])* $vis static $name: $t = $init);
+        $crate::nn_thread_local!($($rest)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr) => {
+        $(#[$attr])* $vis static $name: $crate::os::tls::LocalKey<$t> = {
+            fn __init() -> $t {
+                $init
+            }
+            $crate::os::tls::LocalKey::new(__init)
+        };
+    };
+}
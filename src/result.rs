@@ -0,0 +1,263 @@
+use core::fmt;
+
+// Layout of an nn::Result: the low 9 bits are the module, the next 13 bits are the description
+const MODULE_BITS: u32 = 9;
+const DESCRIPTION_BITS: u32 = 13;
+const MODULE_MASK: u32 = (1 << MODULE_BITS) - 1;
+const DESCRIPTION_MASK: u32 = (1 << DESCRIPTION_BITS) - 1;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Result(u32);
+
+impl Result {
+    pub const SUCCESS: Self = Self(0);
+
+    pub const fn new(module: u32, description: u32) -> Self {
+        let description = (description & DESCRIPTION_MASK) << MODULE_BITS;
+        let module = module & MODULE_MASK;
+        Self(description | module)
+    }
+
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    pub const fn get(self) -> (u32, u32) {
+        (self.module(), self.description())
+    }
+
+    pub const fn module(self) -> u32 {
+        self.0 & MODULE_MASK
+    }
+
+    pub const fn description(self) -> u32 {
+        (self.0 >> MODULE_BITS) & DESCRIPTION_MASK
+    }
+
+    pub const fn error_module(self) -> ErrorModule {
+        ErrorModule::from_raw(self.module())
+    }
+
+    // Descriptions are grouped into ranges by the SDK, e.g. every nn::fs::ResultDataCorrupted is in 4000..5000
+    pub fn is_in_range(self, module: u32, descriptions: core::ops::Range<u32>) -> bool {
+        self.module() == module && descriptions.contains(&self.description())
+    }
+
+    pub const fn is_success(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn is_failure(&self) -> bool {
+        self.0 != 0
+    }
+}
+
+// Formats as the error code shown to players, e.g. 2002-0001
+impl fmt::Display for Result {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:04}", 2000 + self.module(), self.description())
+    }
+}
+
+impl fmt::Debug for Result {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_success() {
+            write!(f, "Result(Success)")
+        } else {
+            write!(f, "Result({}, {:?})", self, self.error_module())
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorModule {
+    Kernel,
+    Fs,
+    Os,
+    Htcs,
+    Ncm,
+    Dd,
+    Lr,
+    Loader,
+    Sf,
+    Hipc,
+    Dmnt,
+    Pm,
+    Ns,
+    BsdSocket,
+    Htc,
+    Sm,
+    Ro,
+    Sdmmc,
+    Spl,
+    Socket,
+    I2c,
+    Gpio,
+    Settings,
+    Nifm,
+    Bluetooth,
+    Vi,
+    Time,
+    Ssl,
+    Account,
+    Am,
+    Erpt,
+    Audio,
+    Http,
+    Fatal,
+    Creport,
+    Hid,
+    Ldn,
+    Capture,
+    Unknown(u32)
+}
+
+impl ErrorModule {
+    pub const fn from_raw(module: u32) -> Self {
+        match module {
+            1 => Self::Kernel,
+            2 => Self::Fs,
+            3 => Self::Os,
+            4 => Self::Htcs,
+            5 => Self::Ncm,
+            6 => Self::Dd,
+            8 => Self::Lr,
+            9 => Self::Loader,
+            10 => Self::Sf,
+            11 => Self::Hipc,
+            13 => Self::Dmnt,
+            15 => Self::Pm,
+            16 => Self::Ns,
+            17 => Self::BsdSocket,
+            18 => Self::Htc,
+            21 => Self::Sm,
+            22 => Self::Ro,
+            24 => Self::Sdmmc,
+            26 => Self::Spl,
+            27 => Self::Socket,
+            101 => Self::I2c,
+            102 => Self::Gpio,
+            105 => Self::Settings,
+            110 => Self::Nifm,
+            113 => Self::Bluetooth,
+            114 => Self::Vi,
+            116 => Self::Time,
+            123 => Self::Ssl,
+            124 => Self::Account,
+            128 => Self::Am,
+            147 => Self::Erpt,
+            153 => Self::Audio,
+            155 => Self::Http,
+            163 => Self::Fatal,
+            168 => Self::Creport,
+            202 => Self::Hid,
+            203 => Self::Ldn,
+            206 => Self::Capture,
+            other => Self::Unknown(other)
+        }
+    }
+
+    pub const fn as_raw(self) -> u32 {
+        match self {
+            Self::Kernel => 1,
+            Self::Fs => 2,
+            Self::Os => 3,
+            Self::Htcs => 4,
+            Self::Ncm => 5,
+            Self::Dd => 6,
+            Self::Lr => 8,
+            Self::Loader => 9,
+            Self::Sf => 10,
+            Self::Hipc => 11,
+            Self::Dmnt => 13,
+            Self::Pm => 15,
+            Self::Ns => 16,
+            Self::BsdSocket => 17,
+            Self::Htc => 18,
+            Self::Sm => 21,
+            Self::Ro => 22,
+            Self::Sdmmc => 24,
+            Self::Spl => 26,
+            Self::Socket => 27,
+            Self::I2c => 101,
+            Self::Gpio => 102,
+            Self::Settings => 105,
+            Self::Nifm => 110,
+            Self::Bluetooth => 113,
+            Self::Vi => 114,
+            Self::Time => 116,
+            Self::Ssl => 123,
+            Self::Account => 124,
+            Self::Am => 128,
+            Self::Erpt => 147,
+            Self::Audio => 153,
+            Self::Http => 155,
+            Self::Fatal => 163,
+            Self::Creport => 168,
+            Self::Hid => 202,
+            Self::Ldn => 203,
+            Self::Capture => 206,
+            Self::Unknown(other) => other
+        }
+    }
+}
+
+impl From<ErrorModule> for u32 {
+    fn from(module: ErrorModule) -> u32 {
+        module.as_raw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use super::{ErrorModule, Result};
+
+    #[test]
+    fn decodes_module_and_description() {
+        let result = Result::from_raw(0x1003);
+        assert_eq!(result.get(), (3, 8));
+        assert_eq!(result.error_module(), ErrorModule::Os);
+        assert_eq!(Result::new(3, 8), result);
+        assert_eq!(Result::new(ErrorModule::Fs.as_raw(), 1).raw(), 0x202);
+    }
+
+    #[test]
+    fn masks_out_of_range_fields() {
+        let result = Result::new(0x200 | 2, 0x2000 | 1);
+        assert_eq!(result.get(), (2, 1));
+        assert_eq!(Result::from_raw(0xFFFF_FFFF).get(), (0x1FF, 0x1FFF));
+    }
+
+    #[test]
+    fn ranges() {
+        let result = Result::new(2, 4001);
+        assert!(result.is_in_range(2, 4000..5000));
+        assert!(!result.is_in_range(2, 3000..4000));
+        assert!(!result.is_in_range(3, 4000..5000));
+    }
+
+    #[test]
+    fn formats_as_error_code() {
+        assert_eq!(format!("{}", Result::from_raw(0x202)), "2002-0001");
+        assert_eq!(format!("{}", Result::new(3, 8)), "2003-0008");
+        assert_eq!(format!("{}", Result::new(168, 1234)), "2168-1234");
+        assert_eq!(format!("{:?}", Result::from_raw(0x202)), "Result(2002-0001, Fs)");
+        assert_eq!(format!("{:?}", Result::SUCCESS), "Result(Success)");
+        assert!(Result::SUCCESS.is_success());
+        assert!(Result::from_raw(0x202).is_failure());
+    }
+
+    #[test]
+    fn error_modules_round_trip() {
+        for raw in 0..0x200 {
+            assert_eq!(ErrorModule::from_raw(raw).as_raw(), raw);
+        }
+        assert_eq!(ErrorModule::from_raw(7), ErrorModule::Unknown(7));
+    }
+}